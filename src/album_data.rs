@@ -7,14 +7,33 @@ use sdl2::{
 use serde::Deserialize;
 use std::path::Path;
 
-use crate::osascript_requests;
+use crate::backend::PlayerBackend;
+use crate::error::{MicromusicError, Result};
 
 #[derive(Deserialize, Debug)]
-pub struct ADOsascriptResponse {
+pub struct AlbumData {
     // pub tracks: Vec<String>,
     pub album: String,
     pub album_artist: String,
-    pub artwork_data: Option<String>,
+    #[serde(deserialize_with = "crate::player_data::deserialize_optional_raw_data")]
+    pub artwork_data: Option<Vec<u8>>,
+    pub genre: Option<String>,
+    pub year: Option<u32>,
+    /// The total number of times the album's tracks have been played, if the player keeps track of it
+    pub play_count: Option<u32>,
+    /// When any of the album's tracks was last played, in seconds since the Unix epoch
+    pub last_played: Option<f64>,
+}
+
+/// A track on an album, as listed in the album detail view
+#[derive(Deserialize)]
+pub struct AlbumTrack {
+    /// Identifies the track to the backend so that it can be queued on its own
+    pub id: String,
+    pub name: String,
+    pub artist: String,
+    /// Length in seconds
+    pub length: f64,
 }
 
 /// A set of resources for displaying information about albums.
@@ -33,6 +52,27 @@ impl<'a> AlbumResources<'a> {
     pub fn album_artist(&self) -> &str {
         &self.base_resources.album_artist
     }
+    pub fn genre(&self) -> Option<&str> {
+        self.base_resources.genre.as_deref()
+    }
+    pub fn year(&self) -> Option<u32> {
+        self.base_resources.year
+    }
+    pub fn play_count(&self) -> Option<u32> {
+        self.base_resources.play_count
+    }
+    pub fn last_played(&self) -> Option<f64> {
+        self.base_resources.last_played
+    }
+    /// The key that identifies the album in the artwork cache and the list of hidden albums
+    pub fn key(&self) -> String {
+        album_key(&self.base_resources.album, &self.base_resources.album_artist)
+    }
+}
+
+/// Encodes an album's artist and title into a string that can be used as a filename
+pub fn album_key(album: &str, album_artist: &str) -> String {
+    BASE64URL_NOPAD.encode(format!("{album_artist}{album}").as_bytes())
 }
 
 /// A subset of AlbumResources that doesn't contain a texture, allowing for it to be passed between threads.
@@ -40,75 +80,77 @@ pub struct BaseAlbumResources {
     // tracks: Vec<String>,
     album: String,
     album_artist: String,
+    genre: Option<String>,
+    year: Option<u32>,
+    play_count: Option<u32>,
+    last_played: Option<f64>,
     artwork_file_path: String,
 }
 
 impl BaseAlbumResources {
     pub fn build(
-        response: ADOsascriptResponse,
+        response: AlbumData,
         artwork_cache_dir: &Path,
         artwork_size: u32,
-    ) -> Self {
-        let filename = format!(
-            "{}.png",
-            BASE64URL_NOPAD
-                .encode(format!("{}{}", &response.album_artist, &response.album).as_bytes())
-        );
-
-        let path = artwork_cache_dir
-            .join(filename)
-            .to_str()
-            .unwrap()
-            .to_owned();
+    ) -> Result<Self> {
+        let filename = format!("{}.png", album_key(&response.album, &response.album_artist));
+
+        let path = artwork_cache_dir.join(filename).to_string_lossy().into_owned();
 
         if let Some(artwork_data) = response.artwork_data {
-            crate::engine::raw_to_cached_image(&artwork_data, (artwork_size, artwork_size), &path)
-                .unwrap();
+            crate::engine::raw_to_cached_image(&artwork_data, (artwork_size, artwork_size), &path)?;
         }
 
-        Self {
+        Ok(Self {
             // tracks: response.tracks,
             album: response.album,
             album_artist: response.album_artist,
+            genre: response.genre.filter(|genre| !genre.is_empty()),
+            year: response.year.filter(|year| *year > 0),
+            play_count: response.play_count,
+            last_played: response.last_played,
             artwork_file_path: path,
-        }
+        })
     }
 
-    pub fn construct_artwork<T>(self, texture_creator: &TextureCreator<T>) -> AlbumResources {
+    pub fn construct_artwork<T>(self, texture_creator: &TextureCreator<T>) -> Result<AlbumResources> {
         sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "best"); // linear filtering
 
-        let artwork = texture_creator
-            .load_texture(&self.artwork_file_path)
-            .unwrap(); // Should never panic
+        let artwork = texture_creator.load_texture(&self.artwork_file_path);
 
         sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest"); // point filtering
 
-        AlbumResources {
+        Ok(AlbumResources {
+            artwork: artwork.map_err(MicromusicError::Artwork)?,
             base_resources: self,
-            artwork,
-        }
+        })
     }
 
-    pub fn get_all_from_music(artwork_size: u32) -> Vec<Self> {
+    pub fn get_all_from_player(backend: &dyn PlayerBackend, artwork_size: u32) -> Result<Vec<Self>> {
         // Create the cache directory at ~/Library/Caches/com.breitnw.micromusic/artwork/
-        let project_dirs = ProjectDirs::from("com", "breitnw", "micromusic")
-            .expect("Unable to get path to cache directory.");
+        let project_dirs = ProjectDirs::from("com", "breitnw", "micromusic").ok_or_else(|| {
+            MicromusicError::Cache(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Unable to get path to cache directory",
+            ))
+        })?;
         let cache_dir: &Path = &project_dirs.cache_dir();
         let artwork_cache_dir: &Path = &cache_dir.join("artwork");
-        std::fs::create_dir_all(artwork_cache_dir).expect("Unable to create cache directory.");
+        std::fs::create_dir_all(artwork_cache_dir).map_err(MicromusicError::Cache)?;
 
         println!("Getting a list of cached albums...");
+        // Files that weren't created by micromusic are ignored
         let album_cache: Vec<String> = std::fs::read_dir(artwork_cache_dir)
-            .unwrap()
-            .map(|file| {
-                let mut filename = file.unwrap().file_name().into_string().unwrap();
-                filename.truncate(filename.len() - 4);
-                String::from_utf8(BASE64URL_NOPAD.decode(filename.as_bytes()).unwrap()).unwrap()
+            .map_err(MicromusicError::Cache)?
+            .filter_map(|file| {
+                let filename = file.ok()?.file_name().into_string().ok()?;
+                let encoded = filename.strip_suffix(".png")?;
+                String::from_utf8(BASE64URL_NOPAD.decode(encoded.as_bytes()).ok()?).ok()
             })
             .collect();
 
-        println!("Getting raw data from Apple Music...");
-        let mut album_data = osascript_requests::get_album_data(album_cache);
+        println!("Getting raw data from the music player...");
+        let mut album_data = backend.get_album_data(album_cache)?;
 
         println!("Removing duplicate albums...");
         album_data.sort_by(|a, b| {
@@ -125,12 +167,69 @@ impl BaseAlbumResources {
         album_data.dedup_by(|a, b| a.album == b.album && a.album_artist == b.album_artist);
 
         println!("Building resources...");
+        // Albums whose artwork can't be cached are left out rather than failing the whole library
         let base_album_resources: Vec<BaseAlbumResources> = album_data
             .into_iter()
-            .map(|response| BaseAlbumResources::build(response, artwork_cache_dir, artwork_size))
+            .filter_map(|response| {
+                let album = format!("{} - {}", response.album_artist, response.album);
+                BaseAlbumResources::build(response, artwork_cache_dir, artwork_size)
+                    .map_err(|e| println!("Skipping {album}: {e}"))
+                    .ok()
+            })
             .collect();
 
         println!("Done!");
-        base_album_resources
+        Ok(base_album_resources)
+    }
+}
+
+/// Limits the albums shown in the album select screen to a genre, a decade or a single year
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AlbumFilter {
+    Genre(String),
+    /// The first year of the decade, e.g. 1990 for the 1990s
+    Decade(u32),
+    Year(u32),
+}
+
+impl AlbumFilter {
+    pub fn matches(&self, album: &AlbumResources) -> bool {
+        match self {
+            AlbumFilter::Genre(genre) => album.genre() == Some(genre.as_str()),
+            AlbumFilter::Decade(decade) => album.year().is_some_and(|year| year / 10 * 10 == *decade),
+            AlbumFilter::Year(year) => album.year() == Some(*year),
+        }
+    }
+
+    /// Whether an album is shown with the given filter, or with no filter at all
+    pub fn allows(filter: Option<&Self>, album: &AlbumResources) -> bool {
+        match filter {
+            Some(filter) => filter.matches(album),
+            None => true,
+        }
+    }
+
+    /// The name of the filter, as shown in the filter chooser
+    pub fn label(&self) -> String {
+        match self {
+            AlbumFilter::Genre(genre) => genre.clone(),
+            AlbumFilter::Decade(decade) => format!("{decade}s"),
+            AlbumFilter::Year(year) => year.to_string(),
+        }
+    }
+
+    /// Gets every filter that matches at least one of the albums, with genres first, then decades, then years
+    pub fn options<'a, 'b: 'a>(albums: impl Iterator<Item = &'a AlbumResources<'b>>) -> Vec<Self> {
+        let mut options: Vec<Self> = albums
+            .flat_map(|album| {
+                let genre = album.genre().map(|genre| AlbumFilter::Genre(genre.to_string()));
+                let decade = album.year().map(|year| AlbumFilter::Decade(year / 10 * 10));
+                let year = album.year().map(AlbumFilter::Year);
+                [genre, decade, year].into_iter().flatten()
+            })
+            .collect();
+        options.sort();
+        options.dedup();
+        options
     }
 }
@@ -0,0 +1,13 @@
+/// Gets the value of a command line option, given either as `--name value` or `--name=value`
+pub fn value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    let mut value = None;
+    while let Some(arg) = args.next() {
+        if arg == name {
+            value = args.next();
+        } else if let Some(v) = arg.strip_prefix(name).and_then(|v| v.strip_prefix('=')) {
+            value = Some(v.to_string());
+        }
+    }
+    value
+}
@@ -0,0 +1,413 @@
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::album_data::{AlbumData, AlbumTrack};
+use crate::config::Config;
+use crate::error::{RequestError, Result};
+use crate::mock_requests::MockBackend;
+use crate::mpd_requests::MpdBackend;
+#[cfg(target_os = "linux")]
+use crate::mpris_requests::MprisBackend;
+use crate::osascript_requests::OsascriptBackend;
+use crate::player_data::{PlayerData, PlayerState, RepeatMode};
+use crate::queue_data::{QueuedAlbum, QueuedTrack};
+use crate::saved_queue::{SavedAlbum, SavedQueue};
+
+pub type PlayerDataSender = Sender<Result<Option<PlayerData>>>;
+
+/// Sends the contents of the micromusic DJ queue to the main thread
+pub type QueueSender = Sender<Result<Vec<QueuedTrack>>>;
+
+/// Sends the tracks on an album to the main thread, along with the album and album artist they were requested for
+pub type AlbumTracksSender = Sender<((String, String), Result<Vec<AlbumTrack>>)>;
+
+/// Sends the albums from the last session's queue to the main thread, so the user can choose whether to restore them
+pub type RestoreSender = Sender<Vec<SavedAlbum>>;
+
+/// Sends errors from requests that don't otherwise report back to the main thread, like queueing an album
+pub type ErrorSender = Sender<RequestError>;
+
+/// A backend shared between the main thread and the threads that make requests to the music player
+pub type SharedBackend = Arc<dyn PlayerBackend>;
+
+/// A music player that micromusic can display and control. Every method blocks until the player has responded, so
+/// they should be called from a secondary thread (see the helper functions in this module).
+pub trait PlayerBackend: Send + Sync {
+    /// Returns information on the state of the music player, or None if nothing is playing
+    fn get_player_data(&self) -> Result<Option<PlayerData>>;
+
+    /// Runs a transport, seeking, volume, playback mode or rating command on the music player
+    fn run_command(&self, command: PlayerCommand) -> Result<()>;
+
+    /// Gets data for every album in the library. Albums in `cached_albums` (album artist + album) already have their
+    /// artwork cached, so the backend may leave their artwork data empty.
+    fn get_album_data(&self, cached_albums: Vec<String>) -> Result<Vec<AlbumData>>;
+
+    /// Appends all of the tracks on an album to the micromusic DJ queue. If `start_playback` is set, playback is started
+    /// if it isn't already active.
+    fn queue_album(&self, album: &str, album_artist: &str, start_playback: bool) -> Result<()>;
+
+    /// Inserts all of the tracks on an album into the micromusic DJ queue right after the current track, starting
+    /// playback if it isn't already active
+    fn play_album_next(&self, album: &str, album_artist: &str) -> Result<()>;
+
+    /// Gets the tracks on an album in the order they're played
+    fn get_album_tracks(&self, album: &str, album_artist: &str) -> Result<Vec<AlbumTrack>>;
+
+    /// Appends tracks to the micromusic DJ queue by the IDs returned from `get_album_tracks`, starting playback if it
+    /// isn't already active
+    fn queue_tracks(&self, ids: &[String]) -> Result<()>;
+
+    /// Creates the micromusic DJ queue if it doesn't exist. Any tracks already in it are removed, unless `keep_tracks` is
+    /// set or the queue is currently playing.
+    fn make_dj_playlist(&self, keep_tracks: bool) -> Result<()>;
+
+    /// Removes tracks that have already been played from the micromusic DJ queue
+    fn clear_played_tracks(&self) -> Result<()>;
+
+    /// Gets the tracks in the micromusic DJ queue that haven't been played yet, in the order they'll be played
+    fn get_queue(&self) -> Result<Vec<QueuedTrack>>;
+
+    /// Removes tracks from the micromusic DJ queue by the IDs returned from `get_queue`. Tracks that have already
+    /// started playing are left alone.
+    fn remove_from_queue(&self, ids: &[String]) -> Result<()>;
+
+    /// Removes every track that hasn't been played yet from the micromusic DJ queue
+    fn clear_queue(&self) -> Result<()>;
+
+    /// Moves tracks in the micromusic DJ queue as a block, by the IDs returned from `get_queue`, so that they're played
+    /// right before the track with the ID `before`, or after every other track if it's None
+    fn move_in_queue(&self, ids: &[String], before: Option<&str>) -> Result<()>;
+}
+
+pub enum PlayerCommand {
+    PlayPause,
+    NextTrack,
+    BackTrack,
+    /// Moves to a position in the current track, in seconds
+    Seek(f64),
+    /// Sets the volume, from 0 to 100
+    SetVolume(u8),
+    SetShuffle(bool),
+    SetRepeat(RepeatMode),
+    Love,
+    Unlove,
+}
+
+/// The names of the backends that are available on this platform
+#[cfg(target_os = "linux")]
+pub const BACKEND_NAMES: &[&str] = &["music", "mpd", "mpris", "mock"];
+#[cfg(not(target_os = "linux"))]
+pub const BACKEND_NAMES: &[&str] = &["music", "mpd", "mock"];
+
+/// Gets a backend by the name passed to the `--backend` command line flag. When rendering headless, the mock backend's
+/// simulated playback is frozen so that frames are reproducible.
+pub fn from_name(name: &str, config: &Config, headless: bool) -> std::result::Result<SharedBackend, String> {
+    match name {
+        "music" => Ok(Arc::new(OsascriptBackend::new(config.playlist_name.clone()))),
+        "mpd" => MpdBackend::new()
+            .map(|backend| Arc::new(backend) as SharedBackend)
+            .map_err(|e| format!("Unable to connect to MPD: {e}")),
+        #[cfg(target_os = "linux")]
+        "mpris" => MprisBackend::new()
+            .map(|backend| Arc::new(backend) as SharedBackend)
+            .map_err(|e| format!("Unable to connect to the D-Bus session bus: {e}")),
+        // The mock backend serves a built-in library unless one is passed with `--mock-library <file.json>`
+        "mock" => {
+            let backend = match crate::args::value("--mock-library") {
+                Some(path) => MockBackend::from_file(Path::new(&path))?,
+                None => MockBackend::demo(),
+            };
+            Ok(Arc::new(if headless { backend.with_clock(Box::new(|| 0.)) } else { backend }))
+        }
+        _ => Err(format!(
+            "Unknown backend \"{name}\", expected one of: {}",
+            BACKEND_NAMES.join(", ")
+        )),
+    }
+}
+
+/// Gets the name of the backend selected on the command line with `--backend <name>`, defaulting to Apple Music
+pub fn name_from_args() -> String {
+    crate::args::value("--backend").unwrap_or_else(|| String::from("music"))
+}
+
+/// Gets the backend selected on the command line with `--backend <name>`, defaulting to Apple Music
+pub fn from_args(config: &Config, headless: bool) -> SharedBackend {
+    from_name(&name_from_args(), config, headless).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Sends information about the music player's state to the main thread
+fn send_player_data(data: Result<Option<PlayerData>>, tx: PlayerDataSender) {
+    tx.send(data)
+        .expect("Couldn't send player data through the channel");
+}
+
+/// Creates a new thread to gather information on the current track and send it to the main thread once complete.
+pub fn send_player_data_async(backend: SharedBackend, tx: PlayerDataSender) {
+    thread::spawn(move || {
+        let data = backend.get_player_data();
+        send_player_data(data, tx);
+    });
+}
+
+/// Periodically gathers information on the current track and sends it to the main thread.
+/// * `poll_interval` - The longest time to wait between requests, in seconds
+pub fn send_player_data_loop(backend: SharedBackend, tx: PlayerDataSender, poll_interval: f64) {
+    thread::spawn(move || {
+        let mut time_remaining = poll_interval;
+        loop {
+            let data = backend.get_player_data();
+            if let Ok(Some(response)) = data.as_ref() {
+                time_remaining = response.track_info.length() - response.player_info.pos();
+            }
+            send_player_data(data, tx.clone());
+            // If the track is almost over, don't sleep the full duration so the info can be updated immediately after it ends
+            thread::sleep(Duration::from_secs_f64(time_remaining.min(poll_interval).max(0.2)));
+        }
+    });
+}
+
+/// Sends an error to the main thread if a request failed. Errors are dropped if the main thread has already quit.
+fn send_error(result: Result<()>, summary: &'static str, error_tx: &ErrorSender) {
+    if let Err(error) = result {
+        let _ = error_tx.send(RequestError { summary, error });
+    }
+}
+
+/// Periodically clears played tracks from the micromusic DJ queue
+/// * `queue_tx` - An MPSC sender to send the rest of the queue to the main thread each time, which radio mode uses to tell when the queue is running low and which keeps the saved queue up to date. Set it to None to disable this behavior.
+pub fn clear_played_tracks_loop(backend: SharedBackend, error_tx: ErrorSender, queue_tx: Option<QueueSender>) {
+    thread::spawn(move || loop {
+        send_error(backend.clear_played_tracks(), "couldn't clear played tracks", &error_tx);
+        if let Some(queue_tx) = &queue_tx {
+            let _ = queue_tx.send(backend.get_queue());
+        }
+        thread::sleep(Duration::from_secs_f64(10.0));
+    });
+}
+
+/// Runs a command on the music player from a secondary thread
+/// * `tx` - An MPSC sender to optionally update the player data on the main thread after the command has completed. Set it to None to disable this behavior.
+pub fn run_command<T>(backend: &SharedBackend, command: PlayerCommand, error_tx: &ErrorSender, tx: T)
+where
+    T: Into<Option<PlayerDataSender>>,
+{
+    let tx = tx.into();
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.run_command(command), "couldn't control the player", &error_tx);
+        if let Some(tx) = tx {
+            send_player_data_async(backend, tx);
+        }
+    });
+}
+
+/// Queues an album from a secondary thread
+pub fn queue_album(backend: &SharedBackend, album: String, album_artist: String, error_tx: &ErrorSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.queue_album(&album, &album_artist, true), "couldn't queue album", &error_tx);
+    });
+}
+
+/// Removes an album that was just queued from the micromusic DJ queue from a secondary thread, for undoing queueing
+pub fn unqueue_album(
+    backend: &SharedBackend,
+    album: String,
+    album_artist: String,
+    play_next: bool,
+    error_tx: &ErrorSender,
+) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        let result = backend.get_queue().and_then(|tracks| {
+            let mut matches = QueuedAlbum::group(tracks)
+                .into_iter()
+                .filter(|queued| queued.album == album && queued.album_artist == album_artist);
+            // An album that was queued to play next is the first copy in the queue, otherwise it's the last
+            let queued = if play_next { matches.next() } else { matches.next_back() };
+            match queued {
+                Some(queued) => backend.remove_from_queue(&queued.ids()),
+                None => Ok(()),
+            }
+        });
+        send_error(result, "couldn't undo queueing", &error_tx);
+    });
+}
+
+/// Queues an album to play next from a secondary thread
+pub fn play_album_next(backend: &SharedBackend, album: String, album_artist: String, error_tx: &ErrorSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.play_album_next(&album, &album_artist), "couldn't queue album", &error_tx);
+    });
+}
+
+/// Plays an album right away from a secondary thread. It's queued to play next, then skipped to if the DJ queue is
+/// already active, since queueing only starts playback when it isn't.
+pub fn play_album_now(
+    backend: &SharedBackend,
+    album: String,
+    album_artist: String,
+    error_tx: &ErrorSender,
+    tx: PlayerDataSender,
+) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        let player_info = backend.get_player_data().ok().flatten().map(|data| data.player_info);
+        let result = backend.play_album_next(&album, &album_artist).and_then(|_| match player_info {
+            Some(info) if info.dj_active() && info.state() != PlayerState::Stopped => {
+                backend.run_command(PlayerCommand::NextTrack)?;
+                if info.state() == PlayerState::Paused {
+                    backend.run_command(PlayerCommand::PlayPause)?;
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        });
+        send_error(result, "couldn't play album", &error_tx);
+        send_player_data_async(backend, tx);
+    });
+}
+
+/// Gets the tracks on an album from a secondary thread
+pub fn send_album_tracks_async(backend: &SharedBackend, album: String, album_artist: String, tx: AlbumTracksSender) {
+    let backend = backend.clone();
+    thread::spawn(move || {
+        let tracks = backend.get_album_tracks(&album, &album_artist);
+        let _ = tx.send(((album, album_artist), tracks));
+    });
+}
+
+/// Queues tracks from a secondary thread
+pub fn queue_tracks(backend: &SharedBackend, ids: Vec<String>, error_tx: &ErrorSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.queue_tracks(&ids), "couldn't queue track", &error_tx);
+    });
+}
+
+/// Creates the micromusic DJ queue from a secondary thread. Then, if there's a saved queue, its albums are sent to the
+/// main thread so that the user can choose whether to restore them. The tracks left in the DJ queue are kept until the
+/// user answers, and are cleared by `reset_dj_playlist` or `restore_queue`.
+pub fn make_dj_playlist(
+    backend: &SharedBackend,
+    saved_queue: Option<SavedQueue>,
+    error_tx: &ErrorSender,
+    restore_tx: RestoreSender,
+) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(
+            backend.make_dj_playlist(saved_queue.is_some()),
+            "couldn't create the DJ playlist",
+            &error_tx,
+        );
+        if let Some(saved_queue) = saved_queue {
+            let _ = restore_tx.send(saved_queue.albums);
+        }
+    });
+}
+
+/// Clears the tracks that were kept in the micromusic DJ queue from a secondary thread, after the user declined to
+/// restore the last session's queue
+pub fn reset_dj_playlist(backend: &SharedBackend, error_tx: &ErrorSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.make_dj_playlist(false), "couldn't create the DJ playlist", &error_tx);
+    });
+}
+
+/// Clears the tracks that were kept in the micromusic DJ queue, then queues the albums from the last session's queue in
+/// order from a secondary thread, without starting playback
+pub fn restore_queue(backend: &SharedBackend, albums: Vec<SavedAlbum>, error_tx: &ErrorSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        if let Err(e) = backend.make_dj_playlist(false) {
+            send_error(Err(e), "couldn't create the DJ playlist", &error_tx);
+            return;
+        }
+
+        // The DJ queue isn't cleared while it's playing, so some of the saved albums may still be in it
+        let tracks = match backend.get_queue() {
+            Ok(tracks) => tracks,
+            Err(e) => {
+                send_error(Err(e), "couldn't restore the queue", &error_tx);
+                return;
+            }
+        };
+        let missing = albums.into_iter().filter(|saved| {
+            !tracks
+                .iter()
+                .any(|track| track.album == saved.album && track.album_artist == saved.album_artist)
+        });
+        for saved in missing {
+            send_error(
+                backend.queue_album(&saved.album, &saved.album_artist, false),
+                "couldn't restore the queue",
+                &error_tx,
+            );
+        }
+    });
+}
+
+/// Gets the contents of the micromusic DJ queue from a secondary thread
+pub fn send_queue_async(backend: &SharedBackend, tx: QueueSender) {
+    let backend = backend.clone();
+    thread::spawn(move || {
+        let _ = tx.send(backend.get_queue());
+    });
+}
+
+/// Removes tracks from the micromusic DJ queue from a secondary thread, then sends the updated queue to the main
+/// thread
+pub fn remove_from_queue(backend: &SharedBackend, ids: Vec<String>, error_tx: &ErrorSender, tx: QueueSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.remove_from_queue(&ids), "couldn't remove from queue", &error_tx);
+        let _ = tx.send(backend.get_queue());
+    });
+}
+
+/// Clears the micromusic DJ queue from a secondary thread, then sends the updated queue to the main thread
+pub fn clear_queue(backend: &SharedBackend, error_tx: &ErrorSender, tx: QueueSender) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.clear_queue(), "couldn't clear the queue", &error_tx);
+        let _ = tx.send(backend.get_queue());
+    });
+}
+
+/// Moves tracks in the micromusic DJ queue from a secondary thread, then sends the updated queue to the main thread
+pub fn move_in_queue(
+    backend: &SharedBackend,
+    ids: Vec<String>,
+    before: Option<String>,
+    error_tx: &ErrorSender,
+    tx: QueueSender,
+) {
+    let backend = backend.clone();
+    let error_tx = error_tx.clone();
+    thread::spawn(move || {
+        send_error(backend.move_in_queue(&ids, before.as_deref()), "couldn't reorder the queue", &error_tx);
+        let _ = tx.send(backend.get_queue());
+    });
+}
@@ -0,0 +1,183 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use sdl2::keyboard::Keycode;
+use serde::Deserialize;
+
+/// User settings that override micromusic's default layout, timing and colors. They're read from `config.json` in the
+/// config directory (e.g. ~/Library/Application Support/com.breitnw.micromusic/), or from the file passed with
+/// `--config <file>`. Every key is optional.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The width and height of the artwork, which is also the width of the window
+    pub artwork_size: u32,
+    /// The height of the area below the artwork that shows the track info
+    pub info_area_height: u32,
+    pub frames_per_second: u64,
+    /// The brightness of icons when they aren't hovered, from 0 to 255
+    pub icon_color_mod_default: u8,
+    /// The brightness of icons when they're hovered, from 0 to 255
+    pub icon_color_mod_hover: u8,
+    /// The opacity of the gradients behind the overlay buttons, from 0 to 255
+    pub gradient_max_alpha: u8,
+    /// The longest time to wait between requests for player data, in seconds
+    pub poll_interval: f64,
+    /// The name of the playlist that queued albums are added to
+    pub playlist_name: String,
+    /// Whether to show the elapsed or remaining time above the transport buttons in the miniplayer overlay
+    pub show_time_readout: bool,
+    /// Whether to queue another album from the library whenever the DJ queue runs low
+    pub radio: bool,
+    /// The number of upcoming tracks in the DJ queue below which radio mode queues another album
+    pub radio_min_tracks: u32,
+    /// Whether the album select screen favors albums that haven't been played in a while over a uniform shuffle
+    pub weighted_shuffle: bool,
+    /// Seeds the shuffle so that albums come up in the same order every launch
+    pub shuffle_seed: Option<u64>,
+    pub keybindings: Keybindings,
+}
+
+/// An action that can be triggered with a keyboard shortcut
+#[derive(Clone, Copy)]
+pub enum KeyAction {
+    PlayPause,
+    NextTrack,
+    BackTrack,
+    /// Loves the current track, or unloves it if it's already loved
+    Love,
+    /// Switches between the miniplayer and album select views
+    ToggleView,
+    Reshuffle,
+    /// Starts or ends a search in the album select view
+    Search,
+    Minimize,
+}
+
+/// The keys that trigger each action, using SDL's key names (e.g. "Space", "Left", "L")
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Keybindings {
+    pub play_pause: String,
+    pub next_track: String,
+    pub back_track: String,
+    pub love: String,
+    pub toggle_view: String,
+    pub reshuffle: String,
+    pub search: String,
+    pub minimize: String,
+}
+
+impl Default for Keybindings {
+    fn default() -> Self {
+        Keybindings {
+            play_pause: String::from("Space"),
+            next_track: String::from("Right"),
+            back_track: String::from("Left"),
+            love: String::from("L"),
+            toggle_view: String::from("Tab"),
+            reshuffle: String::from("R"),
+            search: String::from("F"),
+            minimize: String::from("Escape"),
+        }
+    }
+}
+
+impl Keybindings {
+    /// Maps each bound key to its action. Fails if a key name isn't recognized or a key is bound more than once.
+    pub fn keymap(&self) -> Result<HashMap<Keycode, KeyAction>, String> {
+        let bindings = [
+            ("play_pause", &self.play_pause, KeyAction::PlayPause),
+            ("next_track", &self.next_track, KeyAction::NextTrack),
+            ("back_track", &self.back_track, KeyAction::BackTrack),
+            ("love", &self.love, KeyAction::Love),
+            ("toggle_view", &self.toggle_view, KeyAction::ToggleView),
+            ("reshuffle", &self.reshuffle, KeyAction::Reshuffle),
+            ("search", &self.search, KeyAction::Search),
+            ("minimize", &self.minimize, KeyAction::Minimize),
+        ];
+
+        let mut keymap = HashMap::new();
+        let mut bound_to = HashMap::new();
+        for (name, key_name, action) in bindings {
+            let keycode = Keycode::from_name(key_name)
+                .ok_or_else(|| format!("Unknown key \"{key_name}\" for keybindings.{name}"))?;
+            if let Some(other) = bound_to.insert(keycode, name) {
+                return Err(format!("\"{key_name}\" is bound to both keybindings.{other} and keybindings.{name}"));
+            }
+            keymap.insert(keycode, action);
+        }
+        Ok(keymap)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            artwork_size: 210,
+            info_area_height: 40,
+            frames_per_second: 30,
+            icon_color_mod_default: 150,
+            icon_color_mod_hover: 255,
+            gradient_max_alpha: 140,
+            poll_interval: 3.0,
+            playlist_name: String::from("micromusic DJ"),
+            show_time_readout: true,
+            radio: false,
+            radio_min_tracks: 5,
+            weighted_shuffle: false,
+            shuffle_seed: None,
+            keybindings: Keybindings::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file, falling back to the defaults if there isn't one
+    pub fn load() -> Result<Self, String> {
+        let path = match crate::args::value("--config") {
+            Some(path) => PathBuf::from(path),
+            None => match ProjectDirs::from("com", "breitnw", "micromusic") {
+                Some(project_dirs) => project_dirs.config_dir().join("config.json"),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let file = match std::fs::read_to_string(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(e) => return Err(format!("Unable to read config file {}: {e}", path.display())),
+        };
+        let config: Config = serde_json::from_str(&file)
+            .map_err(|e| format!("Invalid config file {}: {e}", path.display()))?;
+        config
+            .validate()
+            .map_err(|e| format!("Invalid config file {}: {e}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Checks for values that would break the layout or the rendering loop
+    fn validate(&self) -> Result<(), String> {
+        if self.artwork_size < 120 {
+            return Err(format!("artwork_size must be at least 120, got {}", self.artwork_size));
+        }
+        if self.info_area_height < 20 {
+            return Err(format!("info_area_height must be at least 20, got {}", self.info_area_height));
+        }
+        if !(1..=240).contains(&self.frames_per_second) {
+            return Err(format!("frames_per_second must be between 1 and 240, got {}", self.frames_per_second));
+        }
+        if self.poll_interval.is_nan() || self.poll_interval < 0.2 {
+            return Err(format!("poll_interval must be at least 0.2, got {}", self.poll_interval));
+        }
+        if self.playlist_name.is_empty() {
+            return Err(String::from("playlist_name can't be empty"));
+        }
+        if self.radio_min_tracks == 0 {
+            return Err(String::from("radio_min_tracks must be at least 1"));
+        }
+        self.keybindings.keymap()?;
+        Ok(())
+    }
+}
@@ -10,10 +10,11 @@ use sdl2_unifont::renderer::SurfaceRenderer;
 
 use image::{self, imageops};
 
-use hex::FromHex;
+use crate::error::MicromusicError;
 
 pub mod mouse;
 use mouse::MouseState;
+pub mod toast;
 
 //Enum representing button states
 enum ButtonState {
@@ -115,6 +116,12 @@ pub fn update_canvas_scale<T: RenderTarget>(
         .unwrap();
 }
 
+/// Formats a time in seconds as minutes and seconds, e.g. 3:07
+pub fn format_time(secs: f64) -> String {
+    let secs = secs.max(0.) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
 /// Converts a string to a texture
 pub fn text_to_texture<'a, T>(
     text: &str,
@@ -151,35 +158,30 @@ pub fn copy_unscaled<T: RenderTarget>(
     Ok(())
 }
 
-pub fn raw_to_cached_image<'a>(raw_data: &str, size: (u32, u32), cache_path: &str) -> std::result::Result<(), hex::FromHexError> {
-    // Load the raw bytes
-    let bytes = Vec::from_hex(&raw_data[8..raw_data.len() - 2])?;
-
+pub fn raw_to_cached_image(bytes: &[u8], size: (u32, u32), cache_path: &str) -> crate::error::Result<()> {
     // If the caller specified a target image size, resize the image to that size
-    let image = image::load_from_memory(&bytes)
-        .unwrap()
+    let image = image::load_from_memory(bytes)?
         .resize(size.0, size.1, imageops::FilterType::CatmullRom);
 
-    // TODO: shouldn't unwrap this
-    image.save_with_format(cache_path, image::ImageFormat::Png).unwrap();
+    image.save_with_format(cache_path, image::ImageFormat::Png)?;
 
     Ok(())
 } 
 
 pub fn raw_to_texture<'a, 'b, T>(
-    raw_data: &'a str, 
+    bytes: &'a [u8], 
     texture_creator: &'b TextureCreator<T>, 
-) -> std::result::Result<Texture<'b>, hex::FromHexError> {
+) -> crate::error::Result<Texture<'b>> {
     sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "best"); // linear filtering
 
-    // Load the raw bytes
-    let bytes = Vec::from_hex(&raw_data[8..raw_data.len() - 2])?;
     // Load the texture
-    let artwork_texture = texture_creator.load_texture_bytes(&bytes).unwrap();
+    let artwork_texture = texture_creator
+        .load_texture_bytes(bytes)
+        .map_err(MicromusicError::Artwork);
 
     sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest"); // point filtering
 
-    Ok(artwork_texture)
+    artwork_texture
 }
 
 // Shadows
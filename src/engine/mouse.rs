@@ -11,6 +11,10 @@ pub struct MouseState {
 
 #[allow(dead_code)]
 impl MouseState {
+    /// Creates a mouse state from a button bitmask and a position, e.g. to simulate the mouse
+    pub fn new(mouse_state: u32, x: i32, y: i32) -> MouseState {
+        MouseState { mouse_state, x, y }
+    }
     /// Gets the state in the same way as sdl2::mouse:MouseState::new
     pub fn get_state(_e: &sdl2::EventPump) -> MouseState {
         let mut x = 0;
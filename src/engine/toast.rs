@@ -0,0 +1,69 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator};
+
+/// How long a toast is shown for, in seconds
+const TOAST_DURATION: f32 = 3.0;
+/// How long a toast takes to fade out at the end of its duration, in seconds
+const TOAST_FADE_DURATION: f32 = 0.5;
+
+/// A short status message that temporarily covers the info area
+pub struct Toast<'a> {
+    text: String,
+    texture: Texture<'a>,
+    remaining: f32,
+}
+
+impl<'a> Toast<'a> {
+    pub fn new<T>(text: &str, texture_creator: &'a TextureCreator<T>) -> Toast<'a> {
+        let mut texture = super::text_to_texture(
+            text,
+            texture_creator,
+            Color::RGB(255, 255, 255),
+            Color::RGB(0, 0, 0),
+        );
+        texture.set_blend_mode(BlendMode::Blend);
+        Toast {
+            text: text.to_string(),
+            texture,
+            remaining: TOAST_DURATION,
+        }
+    }
+
+    /// Shows a toast with the given text, replacing `toast`. If it's already showing the same text, its timer is reset
+    /// instead so the texture isn't recreated when the same error keeps occurring.
+    pub fn show<T>(toast: &mut Option<Toast<'a>>, text: &str, texture_creator: &'a TextureCreator<T>) {
+        match toast {
+            Some(t) if t.text == text => t.remaining = TOAST_DURATION,
+            _ => *toast = Some(Toast::new(text, texture_creator)),
+        }
+    }
+
+    /// Advances the toast's timer, returning false once it has expired
+    pub fn update(&mut self, dt: f32) -> bool {
+        self.remaining -= dt;
+        self.remaining > 0.
+    }
+
+    /// Draws the toast centered over `rect`, covering anything that was previously drawn there
+    pub fn render<T: RenderTarget>(&mut self, canvas: &mut Canvas<T>, rect: Rect) -> Result<(), String> {
+        let alpha = (self.remaining / TOAST_FADE_DURATION).min(1.0);
+
+        canvas.set_blend_mode(BlendMode::Blend);
+        canvas.set_draw_color(Color::RGBA(0, 0, 0, (alpha * 255.) as u8));
+        canvas.fill_rect(rect)?;
+
+        let query = self.texture.query();
+        self.texture.set_alpha_mod((alpha * 255.) as u8);
+        canvas.copy(
+            &self.texture,
+            None,
+            Rect::new(
+                rect.x() + (rect.width() as i32 - query.width as i32) / 2,
+                rect.y() + (rect.height() as i32 - query.height as i32) / 2,
+                query.width,
+                query.height,
+            ),
+        )
+    }
+}
@@ -0,0 +1,77 @@
+use std::fmt;
+
+/// An error that occurred while communicating with the music player or loading its data
+#[derive(Debug)]
+pub enum MicromusicError {
+    /// The music player isn't running
+    NotRunning,
+    /// A script or command sent to the music player failed
+    Script(String),
+    /// The connection to the music player failed
+    Connection(std::io::Error),
+    /// The music player's response couldn't be decoded
+    Decode(serde_json::Error),
+    /// Artwork data couldn't be decoded
+    Artwork(String),
+    /// The artwork cache couldn't be read or written
+    Cache(std::io::Error),
+}
+
+pub type Result<T> = std::result::Result<T, MicromusicError>;
+
+impl fmt::Display for MicromusicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MicromusicError::NotRunning => write!(f, "The music player is not running"),
+            MicromusicError::Script(e) => write!(f, "Script failed: {e}"),
+            MicromusicError::Connection(e) => write!(f, "Connection to the music player failed: {e}"),
+            MicromusicError::Decode(e) => write!(f, "Couldn't decode the music player's response: {e}"),
+            MicromusicError::Artwork(e) => write!(f, "Couldn't decode artwork: {e}"),
+            MicromusicError::Cache(e) => write!(f, "Couldn't access the artwork cache: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for MicromusicError {}
+
+impl MicromusicError {
+    /// A short description of the error to show to the user when the player data couldn't be fetched
+    pub fn player_data_summary(&self) -> &'static str {
+        match self {
+            MicromusicError::NotRunning => "Music is not running",
+            MicromusicError::Connection(_) => "couldn't reach the player",
+            _ => "couldn't get player data",
+        }
+    }
+}
+
+/// An error from a request made on a secondary thread, along with a short summary of what failed to show to the user
+pub struct RequestError {
+    pub summary: &'static str,
+    pub error: MicromusicError,
+}
+
+impl From<osascript::Error> for MicromusicError {
+    fn from(e: osascript::Error) -> Self {
+        match e {
+            osascript::Error::Io(e) => MicromusicError::Script(e.to_string()),
+            osascript::Error::Json(e) => MicromusicError::Decode(e),
+            osascript::Error::Script(e) => MicromusicError::Script(e),
+        }
+    }
+}
+
+impl From<std::io::Error> for MicromusicError {
+    fn from(e: std::io::Error) -> Self {
+        MicromusicError::Connection(e)
+    }
+}
+
+impl From<image::ImageError> for MicromusicError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => MicromusicError::Cache(e),
+            e => MicromusicError::Artwork(e.to_string()),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, RenderTarget};
+
+use crate::engine::mouse::MouseState;
+
+/// Options for rendering frames to PNG files instead of the screen, set with the `--headless <dir>` command line
+/// option. Rendering happens on SDL's dummy video driver with a software renderer, so no display is needed.
+pub struct HeadlessOptions {
+    /// The directory that frames are written to
+    output_dir: PathBuf,
+    /// The number of frames to render before quitting
+    pub frames: u32,
+    /// Whether to start in the album select view instead of the miniplayer
+    pub album_view: bool,
+    /// The simulated mouse position, if the mouse should be hovering over the window
+    mouse_pos: Option<(i32, i32)>,
+}
+
+impl HeadlessOptions {
+    /// Reads headless options from the command line, or returns None if `--headless` wasn't passed
+    /// * `--frames <n>` - The number of frames to render (default 1)
+    /// * `--view <miniplayer|album>` - The view to render (default miniplayer)
+    /// * `--mouse <x>,<y>` - Simulates the mouse hovering over the window at the given position
+    pub fn from_args() -> Result<Option<Self>, String> {
+        let Some(output_dir) = crate::args::value("--headless") else {
+            return Ok(None);
+        };
+
+        let frames = match crate::args::value("--frames") {
+            Some(frames) => frames
+                .parse()
+                .map_err(|_| format!("Invalid frame count \"{frames}\""))?,
+            None => 1,
+        };
+        let album_view = match crate::args::value("--view").as_deref() {
+            None | Some("miniplayer") => false,
+            Some("album") => true,
+            Some(view) => return Err(format!("Unknown view \"{view}\", expected one of: miniplayer, album")),
+        };
+        let mouse_pos = crate::args::value("--mouse")
+            .map(|pos| {
+                pos.split_once(',')
+                    .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)))
+                    .ok_or_else(|| format!("Invalid mouse position \"{pos}\", expected <x>,<y>"))
+            })
+            .transpose()?;
+
+        std::fs::create_dir_all(&output_dir)
+            .map_err(|e| format!("Unable to create output directory {output_dir}: {e}"))?;
+
+        Ok(Some(HeadlessOptions {
+            output_dir: PathBuf::from(output_dir),
+            frames,
+            album_view,
+            mouse_pos,
+        }))
+    }
+
+    /// Gets the simulated mouse state. The mouse is placed outside of the window unless `--mouse` was passed.
+    pub fn mouse_state(&self) -> MouseState {
+        let (x, y) = self.mouse_pos.unwrap_or((-1, -1));
+        MouseState::new(0, x, y)
+    }
+
+    /// Whether the window should be treated as having input focus, which is only the case when the mouse is hovering
+    pub fn has_focus(&self) -> bool {
+        self.mouse_pos.is_some()
+    }
+
+    /// Saves the contents of the canvas as `frame_<index>.png` in the output directory. Must be called before the
+    /// canvas is presented.
+    pub fn save_frame<T: RenderTarget>(&self, canvas: &Canvas<T>, index: u32) -> Result<(), String> {
+        let (width, height) = canvas.output_size()?;
+        let pixels = canvas.read_pixels(None, PixelFormatEnum::RGB24)?;
+        let path = self.output_dir.join(format!("frame_{index:04}.png"));
+        image::RgbImage::from_raw(width, height, pixels)
+            .ok_or("Canvas size doesn't match its pixel data")?
+            .save(&path)
+            .map_err(|e| format!("Unable to save {}: {e}", path.display()))
+    }
+}
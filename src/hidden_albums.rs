@@ -0,0 +1,53 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+/// Albums that are never shown in the album select screen, identified by the same keys as their cached artwork (see
+/// `album_data::album_key`). Saved as `hidden_albums.json` in the data directory (e.g.
+/// ~/Library/Application Support/com.breitnw.micromusic/).
+#[derive(Serialize, Deserialize, Default)]
+pub struct HiddenAlbums {
+    keys: BTreeSet<String>,
+}
+
+impl HiddenAlbums {
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "breitnw", "micromusic")
+            .map(|project_dirs| project_dirs.data_dir().join("hidden_albums.json"))
+    }
+
+    /// Loads the hidden albums, or returns an empty list if there aren't any or they can't be read
+    pub fn load() -> Self {
+        let Some(file) = Self::path().and_then(|path| std::fs::read_to_string(path).ok()) else {
+            return Self::default();
+        };
+        serde_json::from_str(&file).unwrap_or_else(|e| {
+            println!("Ignoring invalid hidden albums: {e}");
+            Self::default()
+        })
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn hide(&mut self, key: String) {
+        self.keys.insert(key);
+    }
+
+    pub fn unhide(&mut self, key: &str) {
+        self.keys.remove(key);
+    }
+
+    /// Saves the hidden albums so they stay hidden the next time micromusic is launched
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("Unable to get path to data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| format!("Unable to write {}: {e}", path.display()))
+    }
+}
@@ -0,0 +1,282 @@
+use std::rc::Rc;
+
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{BlendMode, Canvas, RenderTarget, Texture, TextureCreator};
+
+use crate::album_data::{AlbumFilter, AlbumResources, AlbumTrack};
+use crate::engine;
+use crate::hidden_albums::HiddenAlbums;
+
+/// The scroll position of a list view, like the queue or the album detail view's tracklist
+#[derive(Default)]
+pub struct ListScroll {
+    pos: i32,
+}
+impl ListScroll {
+    const STEP: i32 = 8;
+    pub fn pos(&self) -> i32 {
+        self.pos
+    }
+    pub fn reset(&mut self) {
+        self.pos = 0;
+    }
+    /// Scrolls by the given number of mouse wheel steps. The position is clamped when the list is next drawn, since
+    /// that's when the height of its contents is known.
+    pub fn scroll(&mut self, steps: i32) {
+        self.pos -= steps * Self::STEP;
+    }
+    /// Keeps the list from scrolling past either end of its contents, returning the clamped position
+    pub fn clamp(&mut self, content_height: i32, list_height: u32) -> i32 {
+        self.pos = self.pos.clamp(0, (content_height - list_height as i32).max(0));
+        self.pos
+    }
+}
+
+/// The search that's filtering the album select screen. The grid shows one page of the albums whose title or album
+/// artist contains the query, taking them out of the shuffled albums while they're shown.
+pub struct Search<'a> {
+    pub query: String,
+    pub matches: Vec<Rc<AlbumResources<'a>>>,
+    pub page: usize,
+    /// The albums that were in the grid before searching, which are put back when the search ends
+    pub previous_grid: Vec<Rc<AlbumResources<'a>>>,
+    /// The query and page number, shown in the info area
+    pub text: Option<Texture<'a>>,
+}
+impl<'a> Search<'a> {
+    pub const PAGE_SIZE: usize = 9;
+    pub fn page_count(&self) -> usize {
+        self.matches.len().div_ceil(Self::PAGE_SIZE).max(1)
+    }
+    /// Finds the albums that match the query, going back to the first page. Only albums that could be shown in the
+    /// grid are searched, so hidden albums and albums left out by the filter are skipped.
+    pub fn update_matches(
+        &mut self,
+        library: &[Rc<AlbumResources<'a>>],
+        hidden_albums: &HiddenAlbums,
+        album_filter: Option<&AlbumFilter>,
+    ) {
+        self.matches = library
+            .iter()
+            .filter(|album| self.matches_query(album))
+            .filter(|album| !hidden_albums.contains(&album.key()))
+            .filter(|album| AlbumFilter::allows(album_filter, album))
+            .cloned()
+            .collect();
+        self.page = 0;
+    }
+    fn matches_query(&self, album: &AlbumResources) -> bool {
+        let query = self.query.to_lowercase();
+        album.title().to_lowercase().contains(&query) || album.album_artist().to_lowercase().contains(&query)
+    }
+    pub fn contains(&self, album: &Rc<AlbumResources<'a>>) -> bool {
+        self.matches.iter().any(|other| Rc::ptr_eq(other, album))
+    }
+    /// Adds an album to the matches if it matches the query, keeping them in the library's order, and returns
+    /// whether it was added
+    pub fn insert_match(&mut self, album: &Rc<AlbumResources<'a>>) -> bool {
+        if !self.matches_query(album) || self.contains(album) {
+            return false;
+        }
+        let key = (album.album_artist(), album.title());
+        let i = self.matches.partition_point(|other| (other.album_artist(), other.title()) < key);
+        self.matches.insert(i, album.clone());
+        true
+    }
+    pub fn update_text<T>(&mut self, texture_creator: &'a TextureCreator<T>) {
+        let text = if self.matches.is_empty() {
+            format!("search: {}_  no matches", self.query)
+        } else {
+            format!("search: {}_  {}/{}", self.query, self.page + 1, self.page_count())
+        };
+        let mut texture =
+            engine::text_to_texture(&text, texture_creator, Color::RGB(255, 255, 255), Color::RGB(0, 0, 0));
+        texture.set_blend_mode(BlendMode::Add);
+        self.text = Some(texture);
+    }
+    pub fn page_albums(&self) -> &[Rc<AlbumResources<'a>>] {
+        let start = (self.page * Self::PAGE_SIZE).min(self.matches.len());
+        &self.matches[start..(start + Self::PAGE_SIZE).min(self.matches.len())]
+    }
+}
+
+/// Data for the queue view. Each album in the queue is shown as a header row with its artwork, followed by a row for
+/// each of its tracks. Albums can be reordered by dragging their header rows.
+pub struct QueueEntry<'a> {
+    /// The album and album artist, used to look up the artwork
+    pub album: (String, String),
+    pub text: Texture<'a>,
+    /// The text and ID of each of the album's tracks
+    pub tracks: Vec<(Texture<'a>, String)>,
+}
+impl QueueEntry<'_> {
+    pub const HEADER_HEIGHT: i32 = 28;
+    pub const TRACK_HEIGHT: i32 = 16;
+    pub fn height(&self) -> i32 {
+        Self::HEADER_HEIGHT + Self::TRACK_HEIGHT * self.tracks.len() as i32
+    }
+    pub fn ids(&self) -> Vec<String> {
+        self.tracks.iter().map(|(_, id)| id.clone()).collect()
+    }
+}
+pub struct QueueDrag {
+    /// The index of the dragged album before it was picked up
+    pub from: usize,
+    /// The index the album will be moved to if it's dropped
+    pub to: usize,
+    /// The distance from the top of the album's header row to the mouse
+    pub grab_offset: i32,
+}
+
+/// Draws the artwork next to an album's header row, or a placeholder if the album isn't in the library (e.g. because
+/// it hasn't loaded yet)
+pub fn draw_queue_artwork<T: RenderTarget>(canvas: &mut Canvas<T>, artwork: Option<&Texture>, row_rect: Rect) {
+    let artwork_rect = Rect::new(4, row_rect.y() + 2, 24, 24);
+    match artwork {
+        Some(artwork) => canvas.copy(artwork, None, artwork_rect).unwrap(),
+        None => {
+            canvas.set_blend_mode(BlendMode::None);
+            canvas.set_draw_color(Color::RGB(40, 40, 40));
+            canvas.fill_rect(artwork_rect).unwrap();
+        }
+    }
+}
+
+/// Draws the text of a row in the queue view, cutting it off at max_width
+pub fn draw_queue_text<T: RenderTarget>(canvas: &mut Canvas<T>, text: &Texture, row_rect: Rect, max_width: u32) {
+    let query = text.query();
+    let width = query.width.min(max_width);
+    canvas
+        .copy(
+            text,
+            Rect::new(0, 0, width, query.height),
+            Rect::new(32, row_rect.y() + (row_rect.height() as i32 - query.height as i32) / 2, width, query.height),
+        )
+        .unwrap();
+}
+
+/// Draws a button at the right end of a hovered row in a list view, like the remove button in the queue view,
+/// returning its rect so that clicks can be checked against it
+pub fn draw_row_button<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    texture_default: &Texture,
+    texture_hover: &Texture,
+    row_rect: Rect,
+    mouse_pos: Point,
+) -> Rect {
+    let button_rect = Rect::new(
+        row_rect.right() - 16,
+        row_rect.y() + (row_rect.height() as i32 - 11) / 2,
+        11,
+        11,
+    );
+    let texture = if button_rect.contains_point(mouse_pos) { texture_hover } else { texture_default };
+    canvas.copy(texture, None, button_rect).unwrap();
+    button_rect
+}
+
+/// Data for the hidden albums view, which lists the hidden albums in the library so that they can be shown again
+pub struct HiddenEntry<'a> {
+    pub album: Rc<AlbumResources<'a>>,
+    pub text: Texture<'a>,
+}
+impl<'a> HiddenEntry<'a> {
+    pub fn new<T>(album: Rc<AlbumResources<'a>>, texture_creator: &'a TextureCreator<T>) -> Self {
+        let mut text = engine::text_to_texture(
+            &format!("{} - {}", album.title(), album.album_artist()),
+            texture_creator,
+            Color::RGB(255, 255, 255),
+            Color::RGB(0, 0, 0),
+        );
+        text.set_blend_mode(BlendMode::Add);
+        HiddenEntry { album, text }
+    }
+}
+
+/// Data for the album detail view, which is opened by clicking an album in the grid. The album's artwork and details
+/// are shown above its tracklist, and tracks can be queued one at a time.
+pub struct AlbumDetail<'a> {
+    pub album: Rc<AlbumResources<'a>>,
+    /// The title, album artist and year, one per line
+    pub lines: Vec<Texture<'a>>,
+    /// The tracks on the album, which are None until they've been received from the music player
+    pub tracks: Option<Vec<DetailTrack<'a>>>,
+}
+pub struct DetailTrack<'a> {
+    pub id: String,
+    pub text: Texture<'a>,
+    pub length: Texture<'a>,
+}
+impl<'a> AlbumDetail<'a> {
+    pub const TRACK_HEIGHT: i32 = 16;
+    pub fn new<T>(album: Rc<AlbumResources<'a>>, texture_creator: &'a TextureCreator<T>) -> Self {
+        let lines = [Some(album.title().to_string()), Some(album.album_artist().to_string())]
+            .into_iter()
+            .chain(std::iter::once(album.year().map(|year| year.to_string())))
+            .flatten()
+            .map(|line| {
+                let mut text = engine::text_to_texture(
+                    &line,
+                    texture_creator,
+                    Color::RGB(255, 255, 255),
+                    Color::RGB(0, 0, 0),
+                );
+                text.set_blend_mode(BlendMode::Add);
+                text
+            })
+            .collect();
+        AlbumDetail { album, lines, tracks: None }
+    }
+    pub fn set_tracks<T>(&mut self, tracks: Vec<AlbumTrack>, texture_creator: &'a TextureCreator<T>, color_mod: u8) {
+        let texture = |text: &str| {
+            let mut texture =
+                engine::text_to_texture(text, texture_creator, Color::RGB(255, 255, 255), Color::RGB(0, 0, 0));
+            texture.set_blend_mode(BlendMode::Add);
+            texture.set_color_mod(color_mod, color_mod, color_mod);
+            texture
+        };
+        self.tracks = Some(
+            tracks
+                .into_iter()
+                .enumerate()
+                .map(|(i, track)| {
+                    // Like in the queue view, the artist is only shown where it differs from the album artist
+                    let title = if track.artist == self.album.album_artist() {
+                        format!("{}. {}", i + 1, track.name)
+                    } else {
+                        format!("{}. {} - {}", i + 1, track.name, track.artist)
+                    };
+                    DetailTrack {
+                        text: texture(&title),
+                        length: texture(&engine::format_time(track.length)),
+                        id: track.id,
+                    }
+                })
+                .collect(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamps_scroll_to_contents() {
+        let mut scroll = ListScroll::default();
+        scroll.scroll(1);
+        assert_eq!(scroll.clamp(200, 100), 0);
+
+        scroll.scroll(-20);
+        assert_eq!(scroll.pos(), 160);
+        assert_eq!(scroll.clamp(200, 100), 100);
+    }
+
+    #[test]
+    fn short_lists_dont_scroll() {
+        let mut scroll = ListScroll::default();
+        scroll.scroll(-3);
+        assert_eq!(scroll.clamp(50, 100), 0);
+    }
+}
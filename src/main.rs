@@ -6,21 +6,18 @@
 
 // CRASHES
 // TODO: Probably panic the whole program when the secondary thread panics
-// TODO: Fix crash when clicking heart button while nothing's playing
 
 // FIXES
 // TODO: Some albums getting rendered multiple times
 
 // BACK BURNER
 // TODO: show a number on queue box
-// TODO: Filter albums by genre
 // TODO: Draw overlay buttons on "not playing" screen
 // TODO: Only re-render info text every frame, not album art
-// TODO: Remember window position on close
 // TODO: Add a way for user to manually clear caches
 // TODO: Load data directly from cache, then update it when loaded from apple music
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 use std::sync::mpsc;
 use std::thread;
@@ -32,8 +29,9 @@ use rust_embed::RustEmbed;
 use sdl2;
 use sdl2::event::{Event, WindowEvent};
 use sdl2::image::LoadTexture;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::libc::c_int;
-use sdl2::mouse::MouseButton;
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
 use sdl2::pixels::Color;
 use sdl2::rect::{Point, Rect};
 use sdl2::render::{BlendMode, Texture, TextureCreator};
@@ -41,26 +39,58 @@ use sdl2::render::{BlendMode, Texture, TextureCreator};
 use sdl2::sys::{SDL_HitTestResult, SDL_Point, SDL_Rect, SDL_SetWindowHitTest, SDL_Window};
 use std::ffi::c_void;
 
-use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use serde::{Deserialize, Serialize};
 
 mod player_data;
-use player_data::PlayerState;
+use player_data::{PlayerState, RepeatMode};
 mod album_data;
-use album_data::AlbumResources;
+use album_data::{AlbumFilter, AlbumResources};
+mod queue_data;
+use queue_data::QueuedAlbum;
+mod args;
+mod backend;
+use backend::PlayerCommand;
 mod osascript_requests;
-use osascript_requests::JXACommand;
+mod mpd_requests;
+mod mock_requests;
+#[cfg(target_os = "linux")]
+mod mpris_requests;
+mod config;
+use config::{Config, KeyAction};
 mod engine;
+mod error;
+mod headless;
+use headless::HeadlessOptions;
+mod window_state;
+use window_state::WindowState;
+mod saved_queue;
+use saved_queue::{SavedAlbum, SavedQueue};
+mod hidden_albums;
+use hidden_albums::HiddenAlbums;
+mod shuffle;
+mod list_views;
+use list_views::{
+    draw_queue_artwork, draw_queue_text, draw_row_button, AlbumDetail, HiddenEntry, ListScroll, QueueDrag, QueueEntry,
+    Search,
+};
 use engine::Button;
 use engine::DrawShadowed;
+use engine::toast::Toast;
 
 use crate::album_data::BaseAlbumResources;
 use crate::player_data::NowPlayingResourceCollection;
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize)]
 enum View {
     Miniplayer,
     AlbumSelect,
+    Queue,
+    Hidden,
+    Filter,
+    AlbumDetail,
 }
 
 // PRIMARY THREAD: Renders a SDL2 interface for users to interact with the application
@@ -69,57 +99,108 @@ fn main() {
 
     sdl2::hint::set("SDL_VIDEO_ALLOW_SCREENSAVER", "1");
 
+    // Load the user's config, which overrides the default layout, timing and colors
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    // If frames should be rendered to files instead of the screen, use a video driver that doesn't need a display
+    let headless = HeadlessOptions::from_args().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    });
+
+    // Select the music player to control
+    let backend_name = backend::name_from_args();
+    let backend = backend::from_args(&config, headless.is_some());
+    if headless.is_some() {
+        std::env::set_var("SDL_VIDEODRIVER", "dummy");
+    }
+
     // Set up a MPSC channel to send player data between threads
     let (player_tx, player_rx) = mpsc::channel();
-
-    // Spawn a secondary thread to periodically gather information on the current track and send it to the main thread
-    osascript_requests::send_player_data_loop(player_tx.clone());
-    osascript_requests::make_dj_playlist();
-    osascript_requests::clear_played_tracks_loop();
+    // Set up a MPSC channel to send errors from requests that don't otherwise report back to the main thread
+    let (error_tx, error_rx) = mpsc::channel();
+    // Set up a MPSC channel to send the contents of the micromusic DJ queue to the main thread
+    let (queue_tx, queue_rx) = mpsc::channel();
+
+    // Spawn a secondary thread to periodically gather information on the current track and send it to the main thread.
+    // Headless renders only fetch it once, so that every frame is drawn from the same snapshot.
+    if headless.is_some() {
+        backend::send_player_data_async(backend.clone(), player_tx.clone());
+    } else {
+        backend::send_player_data_loop(backend.clone(), player_tx.clone(), config.poll_interval);
+    }
+    // Set up a MPSC channel to send the albums from the last session's queue that can be restored to the main thread
+    let (restore_tx, restore_rx) = mpsc::channel();
+    // Headless renders always start from scratch so that they're reproducible
+    let saved_queue = if headless.is_none() { SavedQueue::load(&backend_name) } else { None };
+    backend::make_dj_playlist(&backend, saved_queue, &error_tx, restore_tx);
+    // The played tracks loop also sends the rest of the DJ queue through this channel, which radio mode uses to tell
+    // when the queue is running low and which keeps the queue that's saved on exit up to date
+    let (upcoming_tx, upcoming_rx) = mpsc::channel();
+    backend::clear_played_tracks_loop(backend.clone(), error_tx.clone(), Some(upcoming_tx));
 
     // Initialize SDL
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
+    // SDL starts accepting text input when it's initialized, but it's only needed while searching
+    let text_input = video_subsystem.text_input();
+    text_input.stop();
     let mut event_pump = sdl_context.event_pump().unwrap();
 
-    // Spawn a thread to get all album resources from Apple Music
+    // Spawn a thread to get all album resources from the music player
     let (album_tx, album_rx) = mpsc::channel();
+    let album_backend = backend.clone();
+    let album_artwork_size = config.artwork_size * 2 / 3;
     thread::spawn(move || {
-        let base_album_resources = BaseAlbumResources::get_all_from_music(ARTWORK_SIZE * 2 / 3);
+        let base_album_resources =
+            BaseAlbumResources::get_all_from_player(album_backend.as_ref(), album_artwork_size);
         album_tx.send(base_album_resources).unwrap();
     });
 
     // CONSTANTS =======================================================================================================
 
     // Sizes for the window and canvas
-    const ARTWORK_SIZE: u32 = 210;
-    const INFO_AREA_HEIGHT: u32 = 40;
+    let artwork_size: u32 = config.artwork_size;
+    let info_area_height: u32 = config.info_area_height;
     const INFO_PADDING: u32 = 10;
 
-    const WINDOW_WIDTH: u32 = ARTWORK_SIZE;
-    const WINDOW_HEIGHT: u32 = ARTWORK_SIZE + INFO_AREA_HEIGHT;
-    let window_rect = Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT);
-    let artwork_rect = Rect::new(0, 0, ARTWORK_SIZE, ARTWORK_SIZE);
+    let window_width: u32 = artwork_size;
+    let window_height: u32 = artwork_size + info_area_height;
+    let window_rect = Rect::new(0, 0, window_width, window_height);
+    let artwork_rect = Rect::new(0, 0, artwork_size, artwork_size);
 
-    const THUMBNAIL_SIZE: u32 = ARTWORK_SIZE / 3;
-    const I_THUMBNAIL_SIZE: i32 = ARTWORK_SIZE as i32 / 3;
+    let thumbnail_size: u32 = artwork_size / 3;
+    let i_thumbnail_size: i32 = artwork_size as i32 / 3;
     const THUMBNAIL_SCALE_AMT_HOVER: u32 = 10; // added
     const THUMBNAIL_SCALE_AMT_DRAG: u32 = 20; // subtracted
-    const THUMBNAIL_SIZE_DRAG: u32 = THUMBNAIL_SIZE - THUMBNAIL_SCALE_AMT_DRAG;
+    let thumbnail_size_drag: u32 = thumbnail_size - THUMBNAIL_SCALE_AMT_DRAG;
 
-    const FRAMES_PER_SECOND: u64 = 30;
-    const DT: f32 = 1. / FRAMES_PER_SECOND as f32;
+    let frames_per_second: u64 = config.frames_per_second;
+    let dt: f32 = 1. / frames_per_second as f32;
 
     // WINDOW CREATION =================================================================================================
 
+    // Restore the window's position and view from the last launch. Headless renders always start from scratch so
+    // that they're reproducible.
+    let window_state = if headless.is_none() { WindowState::load() } else { None };
+
     // Create the window
-    let mut window = video_subsystem
-        .window("micromusic", WINDOW_WIDTH, WINDOW_HEIGHT)
-        .position_centered()
-        .allow_highdpi()
-        .borderless()
-        .build()
-        .unwrap();
+    let mut window_builder = video_subsystem.window("micromusic", window_width, window_height);
+    match window_state
+        .as_ref()
+        .and_then(|state| state.position(&video_subsystem, window_width, window_height))
+    {
+        Some((x, y)) => window_builder.position(x, y),
+        None => window_builder.position_centered(),
+    };
+    window_builder.allow_highdpi().borderless();
+    if headless.is_some() {
+        window_builder.hidden();
+    }
+    let mut window = window_builder.build().unwrap();
     window.raise();
 
     // WINDOW HIT TEST -------------------------------------------------------------------------------------------------
@@ -131,8 +212,8 @@ fn main() {
     let add = vec![raw_heap_rect(
         0,
         0,
-        WINDOW_WIDTH as i32,
-        WINDOW_HEIGHT as i32,
+        window_width as i32,
+        window_height as i32,
     )];
     let mut sub = vec![];
 
@@ -167,35 +248,40 @@ fn main() {
 
     // CANVAS AND TEXTURE CREATION =====================================================================================
 
-    //Create a canvas from the window
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    //Create a canvas from the window, rendering in software when headless so that the frames can be read back
+    let mut canvas = if headless.is_some() {
+        window.into_canvas().software().build().unwrap()
+    } else {
+        window.into_canvas().present_vsync().build().unwrap()
+    };
 
     // Get the canvas's texture creator
     let texture_creator = canvas.texture_creator();
 
     //Set up and present the canvas
     canvas.set_draw_color(Color::RGB(0, 0, 0));
-    engine::update_canvas_scale(&mut canvas, WINDOW_WIDTH, WINDOW_HEIGHT);
+    engine::update_canvas_scale(&mut canvas, window_width, window_height);
     canvas.clear();
     canvas.present();
 
     // Load the gradient texture
     // TODO: find a better place for this
     // maybe merge animation functionality with album art animations via a trait
-    const GRADIENT_MAX_ALPHA: u8 = 140;
+    let gradient_max_alpha: u8 = config.gradient_max_alpha;
     const GRADIENT_ALPHA_SPEED: f32 = 3.0;
-    const ENABLE_TOP_GRADIENT_THRESHOLD: i32 = ARTWORK_SIZE as i32 / 9;
-    const ENABLE_BOTTOM_GRADIENT_THRESHOLD: i32 = ARTWORK_SIZE as i32 * 4 / 5;
+    let enable_top_gradient_threshold: i32 = artwork_size as i32 / 9;
+    let enable_bottom_gradient_threshold: i32 = artwork_size as i32 * 4 / 5;
+    let enable_bottom_gradient_x_threshold: i32 = artwork_size as i32 - 66;
 
     // GRADIENT -------------------------------------------------------------------------------
 
-    const GRADIENT_BYTES: &'static [u8] = include_bytes!("../assets/gradient.png");
+    const GRADIENT_BYTES: &[u8] = include_bytes!("../assets/gradient.png");
 
     let mut top_gradient_alpha: u8 = 0;
     let mut bottom_gradient_alpha: u8 = 0;
 
-    let top_gradient_rect = Rect::new(0, 0, WINDOW_WIDTH, THUMBNAIL_SIZE);
-    let bottom_gradient_rect = Rect::new(0, THUMBNAIL_SIZE as i32 * 2, WINDOW_WIDTH, THUMBNAIL_SIZE);
+    let top_gradient_rect = Rect::new(0, 0, window_width, thumbnail_size);
+    let bottom_gradient_rect = Rect::new(0, thumbnail_size as i32 * 2, window_width, thumbnail_size);
 
     // gradient for buttons at the top of the screen
     let mut gradient = texture_creator.load_texture_bytes(GRADIENT_BYTES).unwrap();
@@ -206,7 +292,7 @@ fn main() {
 
     // SHADOW ---------------------------------------------------------------------------------
 
-    const SHADOW_BYTES: &'static [u8] = include_bytes!("../assets/shadow.png");
+    const SHADOW_BYTES: &[u8] = include_bytes!("../assets/shadow.png");
     const SHADOW_OFFSET: i32 = 0;
     const SHADOW_RADIUS: u32 = 30;
     // const SHADOW_ALPHA: i32 = 128; 
@@ -239,29 +325,38 @@ fn main() {
             .collect()
     }
 
-    const ICON_COLOR_MOD_DEFAULT: u8 = 150;
-    const ICON_COLOR_MOD_HOVER: u8 = 255;
+    let icon_color_mod_default: u8 = config.icon_color_mod_default;
+    let icon_color_mod_hover: u8 = config.icon_color_mod_hover;
     let icon_textures_default =
-        load_icons(ICON_COLOR_MOD_DEFAULT, BlendMode::Add, &texture_creator);
-    let icon_textures_hover = load_icons(ICON_COLOR_MOD_HOVER, BlendMode::Add, &texture_creator);
+        load_icons(icon_color_mod_default, BlendMode::Add, &texture_creator);
+    let icon_textures_hover = load_icons(icon_color_mod_hover, BlendMode::Add, &texture_creator);
 
     // BUTTONS ---------------------------------------------------------------------------------------------------------
 
     // Create a map of all of the buttons on the screen
     let button_data = {
-        const A_SIZE: i32 = ARTWORK_SIZE as i32;
+        let a_size: i32 = artwork_size as i32;
         [
             ("album_view", (5, 5)),
             ("miniplayer_view", (5, 5)),
             ("heart_empty", (19, 5)),
             ("heart_filled", (19, 5)),
             ("reshuffle", (19, 5)),
-            ("minimize", (A_SIZE - 30, 5)),
-            ("close", (A_SIZE - 16, 5)),
-            ("pause", (A_SIZE / 2 - 5, A_SIZE - 20)),
-            ("play", (A_SIZE / 2 - 5, A_SIZE - 20)),
-            ("next_track", (A_SIZE / 2 - 6 + 18, A_SIZE - 20)),
-            ("back_track", (A_SIZE / 2 - 6 - 18, A_SIZE - 20)),
+            ("minimize", (a_size - 30, 5)),
+            ("close", (a_size - 16, 5)),
+            ("pause", (a_size / 2 - 5, a_size - 20)),
+            ("play", (a_size / 2 - 5, a_size - 20)),
+            ("next_track", (a_size / 2 - 6 + 18, a_size - 20)),
+            ("back_track", (a_size / 2 - 6 - 18, a_size - 20)),
+            ("shuffle_off", (5, a_size - 20)),
+            ("shuffle_on", (5, a_size - 20)),
+            ("repeat_off", (a_size - 16, a_size - 20)),
+            ("repeat_all", (a_size - 16, a_size - 20)),
+            ("repeat_one", (a_size - 16, a_size - 20)),
+            ("clear_queue", (19, 5)),
+            ("hidden_albums", (33, 5)),
+            ("search", (47, 5)),
+            ("filter", (61, 5)),
         ]
     };
     let mut buttons: HashMap<&'static str, Button> = button_data
@@ -284,14 +379,26 @@ fn main() {
     buttons.insert(
         "queue",
         Button::new(
-            ARTWORK_SIZE as i32 - 36,
-            ARTWORK_SIZE as i32 - 36,
+            artwork_size as i32 - 36,
+            artwork_size as i32 - 36,
             &icon_textures_default["queue_closed.png"],
             &icon_textures_hover["queue_open.png"],
             &icon_textures_hover["queue_open.png"],
         ),
     );
+    // Add the play next box beside it, which inserts albums right after the current track instead of at the end
+    buttons.insert(
+        "play_next",
+        Button::new(
+            artwork_size as i32 - 66,
+            artwork_size as i32 - 36,
+            &icon_textures_default["play_next_closed.png"],
+            &icon_textures_hover["play_next_open.png"],
+            &icon_textures_hover["play_next_open.png"],
+        ),
+    );
     let mut queue_button_squash_frame = 0;
+    let mut play_next_button_squash_frame = 0;
 
 
     // MUTABLE STATE ===================================================================================================
@@ -318,15 +425,15 @@ fn main() {
         pub state: ItemState,
     }
     impl AlbumViewItem<'_> {
-        fn get_target_pos(item_col_i: usize, drag_placeholder_x: Option<usize>) -> usize {
+        fn get_target_pos(item_col_i: usize, drag_placeholder_x: Option<usize>, thumbnail_size: u32) -> usize {
             let mut item_col_i = item_col_i;
             let drag_placeholder_x = drag_placeholder_x.filter(|x| item_col_i >= *x);
             if drag_placeholder_x.is_some() {
                 item_col_i += 1;
             }
-            item_col_i as usize * ARTWORK_SIZE as usize / 3
+            item_col_i as usize * thumbnail_size as usize
         }
-        fn update(&mut self, target_x: f32) {
+        fn update(&mut self, target_x: f32, dt: f32) {
             let dist_from_target = self.x_pos - target_x;
 
             match &mut self.state {
@@ -340,12 +447,12 @@ fn main() {
                         self.x_vel = 0.0;
                         self.x_pos = target_x;
                     }
-                    self.x_pos += self.x_vel * DT;
+                    self.x_pos += self.x_vel * dt;
                 }
                 ItemState::Freeze { timer } => {
                     // Freeze any thumbnails with the freeze timers greater than 0. Primarily used to stagger movements
                     if *timer > 0. {
-                        *timer -= DT;
+                        *timer -= dt;
                     } else {
                         *timer = 0.;
                         self.state = ItemState::Default;
@@ -355,7 +462,7 @@ fn main() {
                     duration, current, ..
                 } => {
                     if current < duration {
-                        *current += DT;
+                        *current += dt;
                     } else {
                         self.state = ItemState::Default;
                     }
@@ -384,6 +491,86 @@ fn main() {
         }
     }
 
+    // Takes the given albums out of the shuffled albums, keeping the rest in order. Returns the ones that were found in
+    // the order they were given, since albums that are being dragged or queued aren't in the shuffled albums.
+    fn take_from_queue<'a>(
+        album_view_queue: &mut Queue<Rc<AlbumResources<'a>>>,
+        albums: &[Rc<AlbumResources<'a>>],
+    ) -> Vec<Rc<AlbumResources<'a>>> {
+        let mut found = Vec::new();
+        for _ in 0..album_view_queue.size() {
+            let album = album_view_queue.remove().unwrap();
+            if albums.iter().any(|a| Rc::ptr_eq(a, &album)) {
+                found.push(album);
+            } else {
+                album_view_queue.add(album).unwrap();
+            }
+        }
+        found.sort_by_key(|album| albums.iter().position(|a| Rc::ptr_eq(a, album)));
+        found
+    }
+
+    // Puts the albums in the grid back into the shuffled albums, then slides up to nine new albums into the grid one
+    // column at a time
+    fn refill_grid<'a>(
+        album_view_rows: &mut [Vec<AlbumViewItem<'a>>; 3],
+        album_view_queue: &mut Queue<Rc<AlbumResources<'a>>>,
+        next_albums: impl FnOnce(&mut Queue<Rc<AlbumResources<'a>>>) -> Vec<Rc<AlbumResources<'a>>>,
+        thumbnail_size: u32,
+        artwork_size: u32,
+    ) {
+        for row in album_view_rows.iter_mut() {
+            row.drain(..).for_each(|item| {
+                album_view_queue.add(item.album).unwrap();
+            });
+        }
+        for (i, album) in next_albums(album_view_queue).into_iter().take(9).enumerate() {
+            album_view_rows[i % 3].push(AlbumViewItem {
+                album,
+                x_pos: AlbumViewItem::get_target_pos(i / 3, None, thumbnail_size) as f32 + artwork_size as f32,
+                x_vel: 0.0,
+                state: ItemState::Freeze {
+                    timer: (i % 3 * 3 + i / 3) as f32 * 0.03,
+                },
+            })
+        }
+    }
+
+    // Sorts every album that isn't hidden into the shuffled albums or the albums that are left out by the filter,
+    // reshuffling them, then refills the grid
+    fn apply_filter<'a>(
+        album_filter: Option<&AlbumFilter>,
+        shuffle: impl FnOnce(&mut Vec<Rc<AlbumResources<'a>>>),
+        album_view_rows: &mut [Vec<AlbumViewItem<'a>>; 3],
+        album_view_queue: &mut Queue<Rc<AlbumResources<'a>>>,
+        filtered_out_albums: &mut Vec<Rc<AlbumResources<'a>>>,
+        thumbnail_size: u32,
+        artwork_size: u32,
+    ) {
+        let mut albums = std::mem::take(filtered_out_albums);
+        for row in album_view_rows.iter_mut() {
+            albums.extend(row.drain(..).map(|item| item.album));
+        }
+        while let Ok(album) = album_view_queue.remove() {
+            albums.push(album);
+        }
+        shuffle(&mut albums);
+        for album in albums {
+            if AlbumFilter::allows(album_filter, &album) {
+                album_view_queue.add(album).unwrap();
+            } else {
+                filtered_out_albums.push(album);
+            }
+        }
+        refill_grid(
+            album_view_rows,
+            album_view_queue,
+            |album_view_queue| (0..9).map_while(|_| album_view_queue.remove().ok()).collect(),
+            thumbnail_size,
+            artwork_size,
+        );
+    }
+
     // VARIABLES -------------------------------------------------------------------------------------------------------
 
     let mut album_view_queue: Queue<Rc<AlbumResources>> = Queue::new();
@@ -391,16 +578,262 @@ fn main() {
     let mut dragged_item: Option<AlbumViewItem> = None;
     let mut dragged_item_pos: [f32; 2] = [0.0, 0.0];
     let mut drag_placeholder_loc: Option<[usize; 2]> = None;
-    let mut queueing_albums: Vec<AlbumViewItem> = Vec::new();
+    // The column and row the dragged album was picked up from, so that it can be put back there if dismissing it is
+    // undone
+    let mut dragged_item_origin: [usize; 2] = [0, 0];
+    // Where the mouse was when the dragged album was picked up. If it's dropped without moving much, it was clicked.
+    let mut drag_start_pos = Point::new(0, 0);
+    const CLICK_DISTANCE: i32 = 3;
+    // Albums that are animating into a queue box, along with the name of the box's button
+    let mut queueing_albums: Vec<(AlbumViewItem, &'static str)> = Vec::new();
+    // Every album in the library by title and album artist, so that their artwork can be shown in the queue view
+    let mut album_lookup: HashMap<(String, String), Rc<AlbumResources>> = HashMap::new();
+    // Every album in the library sorted by album artist and title, which is the order search results are listed in
+    let mut library: Vec<Rc<AlbumResources>> = Vec::new();
+    let mut search: Option<Search> = None;
+    // Shuffles the albums in the album select screen, seeded from the config if the order should be repeatable
+    let mut rng = match config.shuffle_seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        // Headless renders are always seeded so that they're reproducible
+        None if headless.is_some() => StdRng::seed_from_u64(0),
+        None => StdRng::from_entropy(),
+    };
+    // The time that weighted shuffles measure how long ago albums were played from, which headless renders fix so
+    // that they're reproducible
+    let shuffle_time = || if headless.is_some() { 0. } else { shuffle::now() };
+    // The filter that limits which albums are shuffled into the grid, and the albums it leaves out
+    let mut album_filter: Option<AlbumFilter> = None;
+    let mut filtered_out_albums: Vec<Rc<AlbumResources>> = Vec::new();
+    // Each filter in the filter chooser, starting with one to show every album, along with its label. Only filters
+    // that match at least one album in the library are listed.
+    let mut filter_options: Vec<(Option<AlbumFilter>, Texture)> = Vec::new();
+    let mut filter_scroll = ListScroll::default();
+    // The index of the hovered filter option, set when it's drawn so that clicks can be checked against it
+    let mut hovered_filter_option: Option<usize> = None;
+    const FILTER_OPTION_HEIGHT: i32 = 16;
+
+    // The albums in the queue view, which are None until the queue has been received from the music player
+    let mut queue_entries: Option<Vec<QueueEntry>> = None;
+    let mut queue_drag: Option<QueueDrag> = None;
+    let mut queue_scroll = ListScroll::default();
+    // The queue is requested again whenever this reaches zero while the queue view is open
+    let mut queue_refresh_timer: f32 = 0.;
+    const QUEUE_REFRESH_INTERVAL: f32 = 5.0;
+    let queue_list_rect = Rect::new(0, 20, artwork_size, artwork_size - 20);
+    // The remove button of the hovered row and the IDs it removes, set when it's drawn so that clicks can be checked
+    // against it
+    let mut queue_remove_target: Option<(Rect, Vec<String>)> = None;
+    let status_text_texture = |text: &str| {
+        let mut texture =
+            engine::text_to_texture(text, &texture_creator, Color::RGB(255, 255, 255), Color::RGB(0, 0, 0));
+        texture.set_blend_mode(BlendMode::Add);
+        texture.set_color_mod(icon_color_mod_default, icon_color_mod_default, icon_color_mod_default);
+        texture
+    };
+    let queue_loading_texture = status_text_texture("loading queue");
+    let queue_empty_texture = status_text_texture("queue is empty");
+
+    // Albums that are never shown in the album select screen. Headless renders ignore them so that they're
+    // reproducible.
+    let mut hidden_albums = if headless.is_none() { HiddenAlbums::load() } else { HiddenAlbums::default() };
+    // Set when an album is hidden or shown again, so that the hidden albums are saved at most once per frame
+    let mut hidden_albums_changed = false;
+    let mut hidden_entries: Vec<HiddenEntry> = Vec::new();
+    let mut hidden_scroll = ListScroll::default();
+    // The remove button of the hovered row in the hidden albums view and the index of its album
+    let mut hidden_unhide_target: Option<(Rect, usize)> = None;
+    let hidden_empty_texture = status_text_texture("no hidden albums");
+
+    // The album detail view, laid out with the artwork in the top left, the album's details and the play and queue
+    // options beside it, and the tracklist below
+    let mut album_detail: Option<AlbumDetail> = None;
+    let (album_tracks_tx, album_tracks_rx) = mpsc::channel();
+    let mut detail_scroll = ListScroll::default();
+    const DETAIL_TEXT_X: i32 = 76;
+    let detail_artwork_rect = Rect::new(6, 24, 64, 64);
+    let detail_list_rect = Rect::new(0, 92, artwork_size, artwork_size - 92);
+    let mut detail_action_textures = ["play", "queue"].map(status_text_texture);
+    let detail_action_rects = {
+        let mut x = DETAIL_TEXT_X;
+        detail_action_textures.each_ref().map(|texture| {
+            let query = texture.query();
+            let rect = Rect::new(x, 72, query.width, query.height);
+            x += query.width as i32 + 12;
+            rect
+        })
+    };
+    // The queue button of the hovered track and the track's ID, set when it's drawn so that clicks can be checked
+    // against it
+    let mut detail_queue_target: Option<(Rect, String)> = None;
+    let tracks_loading_texture = status_text_texture("loading tracks");
+    let tracks_empty_texture = status_text_texture("no tracks");
+
+    // The prompt that's shown over the info area when the last session's queue can be restored, which holds the albums
+    // that would be queued again. Its question and options are laid out side by side.
+    let mut restore_prompt: Option<Vec<SavedAlbum>> = None;
+    // The albums in the DJ queue, in the order they'll be played, which are saved on exit so they can be restored. It's
+    // replaced whenever the queue is received from the player, and updated as albums are queued in between.
+    let mut queued_albums: Vec<SavedAlbum> = Vec::new();
+    let mut restore_prompt_textures = ["restore queue?", "yes", "no"].map(status_text_texture);
+    let restore_prompt_rects = {
+        const RESTORE_PROMPT_SPACING: u32 = 12;
+        let sizes = restore_prompt_textures.each_ref().map(|texture| {
+            let query = texture.query();
+            (query.width, query.height)
+        });
+        let total_width: u32 = sizes.iter().map(|(width, _)| width + RESTORE_PROMPT_SPACING).sum::<u32>()
+            - RESTORE_PROMPT_SPACING;
+        let mut x = (artwork_size as i32 - total_width as i32) / 2;
+        sizes.map(|(width, height)| {
+            let rect = Rect::new(x, (artwork_size + (info_area_height - height) / 2) as i32, width, height);
+            x += (width + RESTORE_PROMPT_SPACING) as i32;
+            rect
+        })
+    };
 
     // State variables for the rendering loop
     let mut now_playing_resources: NowPlayingResourceCollection =
         NowPlayingResourceCollection::build(None, &texture_creator);
-    let mut last_snapshot_time = Instant::now();
+    // The time in seconds that the player position is interpolated with. Headless renders advance it by exactly one
+    // frame's time per frame instead of following the system clock, so that they're reproducible.
+    let start_time = Instant::now();
+    let mut last_snapshot_time: f64 = 0.;
     let mut info_scroll_pos: f32 = 0.;
     const INFO_SPACING: i32 = 50;
+    let info_rect = Rect::new(0, artwork_size as i32, window_width, info_area_height);
+
+    // The strip at the bottom of the artwork that can be clicked or dragged to seek
+    const SEEK_BAR_HEIGHT: u32 = 6;
+    let seek_rect = Rect::new(0, (artwork_size - SEEK_BAR_HEIGHT) as i32, artwork_size, SEEK_BAR_HEIGHT);
+    // Converts an x coordinate on the seek bar to a position in a track of the given length
+    let seek_bar_pos = |x: i32, length: f64| (x as f64 / artwork_size as f64).clamp(0., 1.) * length;
+    // The position that will be seeked to when the mouse is released, set while the seek bar is being dragged
+    let mut seek_preview: Option<f64> = None;
+
+    // The elapsed or remaining time shown in the miniplayer overlay. The texture is only recreated when the text changes,
+    // and the rect is set when it's drawn so that clicks can be checked against it.
+    let mut time_readout: Option<(String, Texture)> = None;
+    let mut time_readout_rect: Option<Rect> = None;
+    let mut show_remaining_time = false;
+
+    // The volume bar, which fades in when the volume is changed with the scroll wheel and fades out after a delay
+    const VOLUME_STEP: i32 = 2;
+    const VOLUME_BAR_MAX_ALPHA: u8 = 200;
+    const VOLUME_BAR_DURATION: f32 = 1.0;
+    let volume_bar_rect = Rect::new(artwork_size as i32 - 14, artwork_size as i32 / 4, 4, artwork_size / 2);
+    let mut volume_bar_alpha: u8 = 0;
+    let mut volume_bar_timer: f32 = 0.;
+
+    // A status message shown over the info area, e.g. when a request to the music player fails
+    let mut toast: Option<Toast> = None;
+    // Actions that can be undone with Cmd/Ctrl+Z, most recent last
+    enum UndoAction<'a> {
+        Dismiss {
+            album: Rc<AlbumResources<'a>>,
+            loc: [usize; 2],
+        },
+        Hide {
+            album: Rc<AlbumResources<'a>>,
+            loc: [usize; 2],
+        },
+        Queue {
+            title: String,
+            album_artist: String,
+            play_next: bool,
+        },
+    }
+    const UNDO_LIMIT: usize = 20;
+    let mut undo_stack: Vec<UndoAction> = Vec::new();
+
+    // Adds an action to the undo stack, forgetting the oldest one once there are too many
+    fn push_undo<'a>(undo_stack: &mut Vec<UndoAction<'a>>, action: UndoAction<'a>) {
+        undo_stack.push(action);
+        if undo_stack.len() > UNDO_LIMIT {
+            undo_stack.remove(0);
+        }
+    }
+
+    // Slides an album into the grid at the given column and row, sending the last album in the row back to the
+    // shuffled albums to make room
+    fn return_to_grid<'a>(
+        album_view_rows: &mut [Vec<AlbumViewItem<'a>>; 3],
+        album_view_queue: &mut Queue<Rc<AlbumResources<'a>>>,
+        album: Rc<AlbumResources<'a>>,
+        loc: [usize; 2],
+        artwork_size: u32,
+    ) {
+        let row = &mut album_view_rows[loc[1]];
+        if row.len() >= 3 {
+            album_view_queue.add(row.pop().unwrap().album).unwrap();
+        }
+        row.insert(
+            loc[0].min(row.len()),
+            AlbumViewItem {
+                album,
+                state: ItemState::Default,
+                x_pos: artwork_size as f32,
+                x_vel: 0.0,
+            },
+        );
+    }
+
+    // Whether the album data has been received from the music player
+    let mut albums_loaded = false;
+    // State for radio mode, which queues albums from the shuffled library when the DJ queue runs low. Albums that were
+    // played or queued recently are skipped, and so are albums that were dragged out of the album grid.
+    const RADIO_HISTORY_LENGTH: usize = 30;
+    // How long to wait after radio mode queues an album before checking the queue again, so the album isn't queued
+    // twice while it's still being added
+    const RADIO_COOLDOWN: f32 = 20.0;
+    // Recently played albums, by title and album artist, since different artists can have albums with the same title
+    let mut recent_albums: VecDeque<(String, String)> = VecDeque::new();
+    let mut dismissed_albums: HashSet<(String, String)> = HashSet::new();
+    let mut radio_cooldown: f32 = 0.;
+    // Whether the DJ queue was playing the last time anything was, so that radio mode doesn't take over playback
+    // from other playlists
+    let mut dj_was_active = false;
+
+    // Remembers an album as recently played, forgetting the oldest one once there are too many
+    fn remember_recent_album(recent_albums: &mut VecDeque<(String, String)>, title: &str, album_artist: &str) {
+        if recent_albums.back().map(|(t, a)| (t.as_str(), a.as_str())) != Some((title, album_artist)) {
+            recent_albums.push_back((title.to_string(), album_artist.to_string()));
+        }
+        if recent_albums.len() > RADIO_HISTORY_LENGTH {
+            recent_albums.pop_front();
+        }
+    }
+
+    // Records an album that was just queued in the locally tracked DJ queue
+    fn remember_queued_album(queued_albums: &mut Vec<SavedAlbum>, title: &str, album_artist: &str, play_next: bool) {
+        let album = SavedAlbum {
+            album: title.to_string(),
+            album_artist: album_artist.to_string(),
+        };
+        if play_next {
+            queued_albums.insert(0, album);
+        } else {
+            queued_albums.push(album);
+        }
+    }
+
+    // Whether the last request for player data succeeded, used to avoid overwriting the saved queue with an empty one
+    // when the player's queue was never seen
+    let mut player_reachable = false;
+
+    let mut current_view = match (&headless, &window_state) {
+        (Some(options), _) if options.album_view => View::AlbumSelect,
+        (None, Some(state)) => state.view,
+        _ => View::Miniplayer,
+    };
+    if current_view == View::AlbumSelect && headless.is_none() {
+        Toast::show(&mut toast, "library still loading", &texture_creator);
+    }
+
+    // Keyboard shortcuts, which are validated when the config is loaded
+    let keymap = config.keybindings.keymap().unwrap();
 
-    let mut current_view = View::Miniplayer;
+    // The number of frames that have been rendered, used to name frames in headless mode
+    let mut frame_count: u32 = 0;
 
     // A boolean that specifies whether the user is currently dragging the window. Set when a window drag event occurs,
     // reset when the mouse button is released
@@ -409,15 +842,33 @@ fn main() {
     // RENDERING LOOP ==================================================================================================
 
     'running: loop {
+        let now = match &headless {
+            Some(_) => frame_count as f64 * dt as f64,
+            None => start_time.elapsed().as_secs_f64(),
+        };
+
         // INPUT EVENTS ------------------------------------------------------------------------------------------------
 
         // Mouse state
-        let mouse_state =
-            engine::mouse::MouseState::get_relative_state(&event_pump, canvas.window());
-        let window_input_focus = &canvas.window().window_flags() & 512 == 512; // input focus: 512, mouse focus: 1024
+        let mouse_state = match &headless {
+            Some(options) => options.mouse_state(),
+            None => engine::mouse::MouseState::get_relative_state(&event_pump, canvas.window()),
+        };
+        let window_input_focus = match &headless {
+            Some(options) => options.has_focus(),
+            None => &canvas.window().window_flags() & 512 == 512, // input focus: 512, mouse focus: 1024
+        };
+
+        // The total volume change from scrolling this frame, so only one request is made per frame
+        let mut volume_change = 0;
+        // Whether the search results need to be shown again, or the search should end
+        let mut search_page_changed = false;
+        let mut search_ended = false;
 
         // Iterate through the input events
         for event in event_pump.poll_iter() {
+            // The name of the button to activate, if one was clicked or its keyboard shortcut was pressed
+            let mut pressed_button = None;
             match event {
                 Event::Quit { .. } => {
                     break 'running;
@@ -431,31 +882,97 @@ fn main() {
                     if Button::get_hovered_from_hash(&buttons, x, y).is_some() {
                         continue;
                     }
+                    if current_view == View::Miniplayer
+                        && seek_rect.contains_point((x, y))
+                        && now_playing_resources.player_info.state() != PlayerState::Stopped
+                    {
+                        seek_preview = Some(seek_bar_pos(x, now_playing_resources.track_info.length()));
+                    }
+                    // Pick up the album whose header row was clicked in the queue view, unless the remove button was
+                    // clicked
+                    if let Some(entries) = queue_entries.as_ref().filter(|_| {
+                        current_view == View::Queue
+                            && queue_list_rect.contains_point((x, y))
+                            && !queue_remove_target.as_ref().is_some_and(|(rect, _)| rect.contains_point((x, y)))
+                    }) {
+                        let mut entry_y = queue_list_rect.y() - queue_scroll.pos();
+                        for (i, entry) in entries.iter().enumerate() {
+                            if (entry_y..entry_y + QueueEntry::HEADER_HEIGHT).contains(&y) {
+                                queue_drag = Some(QueueDrag {
+                                    from: i,
+                                    to: i,
+                                    grab_offset: y - entry_y,
+                                });
+                                break;
+                            }
+                            entry_y += entry.height();
+                        }
+                    }
                     if current_view == View::AlbumSelect
                         && artwork_rect.contains_point(mouse_state.pos())
                     {
-                        let hovered_album_loc = mouse_state.pos() / I_THUMBNAIL_SIZE;
-                        let target_row = album_view_rows
-                            .get_mut(hovered_album_loc.y as usize)
-                            .unwrap();
-
-                        if !target_row.is_empty() {
-                            let mut target_item = target_row.remove(hovered_album_loc.x as usize);
+                        let hovered_album_loc = mouse_state.pos() / i_thumbnail_size;
+                        let loc = [hovered_album_loc.x as usize, hovered_album_loc.y as usize];
+                        let target_row = &mut album_view_rows[loc[1]];
+
+                        // Rows are left short when a search page has fewer than nine matches, or when an album is
+                        // hidden with no shuffled albums left to replace it, so the gap can't be picked up
+                        if loc[0] < target_row.len() {
+                            let mut target_item = target_row.remove(loc[0]);
                             target_item.state = ItemState::Dragged;
                             dragged_item_pos = [
                                 target_item.x_pos + (THUMBNAIL_SCALE_AMT_DRAG / 2) as f32,
-                                (I_THUMBNAIL_SIZE * hovered_album_loc.y) as f32
+                                (i_thumbnail_size * hovered_album_loc.y) as f32
                                     + (THUMBNAIL_SCALE_AMT_DRAG / 2) as f32,
                             ];
                             dragged_item = Some(target_item);
-
-                            target_row.push(AlbumViewItem {
-                                album: album_view_queue.remove().unwrap(),
+                            dragged_item_origin = loc;
+                            drag_start_pos = mouse_state.pos();
+
+                            // While searching, the rest of the page closes the gap instead. A filter may also leave
+                            // no shuffled albums to replace the one that was picked up.
+                            if search.is_none() {
+                                if let Ok(next_album) = album_view_queue.remove() {
+                                    target_row.push(AlbumViewItem {
+                                        album: next_album,
+                                        state: ItemState::Default,
+                                        x_pos: artwork_size as f32,
+                                        x_vel: 0.0,
+                                    });
+                                }
+                            }
+                        }
+                    }
+                }
+                // Right-clicking an album in the grid hides it from the album select screen for good
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Right,
+                    ..
+                } if current_view == View::AlbumSelect
+                    && dragged_item.is_none()
+                    && artwork_rect.contains_point(mouse_state.pos()) =>
+                {
+                    let hovered_album_loc = mouse_state.pos() / i_thumbnail_size;
+                    let loc = [hovered_album_loc.x as usize, hovered_album_loc.y as usize];
+                    let row = &mut album_view_rows[loc[1]];
+                    if loc[0] < row.len() {
+                        let album = row.remove(loc[0]).album;
+                        if let Some(search) = search.as_mut() {
+                            search.matches.retain(|other| !Rc::ptr_eq(other, &album));
+                            search.update_text(&texture_creator);
+                        } else if let Ok(next_album) = album_view_queue.remove() {
+                            row.push(AlbumViewItem {
+                                album: next_album,
                                 state: ItemState::Default,
-                                x_pos: ARTWORK_SIZE as f32,
+                                x_pos: artwork_size as f32,
                                 x_vel: 0.0,
                             });
                         }
+                        hidden_albums.hide(album.key());
+                        hidden_albums_changed = true;
+                        hidden_entries.insert(0, HiddenEntry::new(album.clone(), &texture_creator));
+                        push_undo(&mut undo_stack, UndoAction::Hide { album, loc });
+                        Toast::show(&mut toast, "album hidden", &texture_creator);
                     }
                 }
                 Event::MouseButtonUp {
@@ -464,28 +981,57 @@ fn main() {
                     mouse_btn: MouseButton::Left,
                     ..
                 } => {
+                    if let Some(pos) = seek_preview.take() {
+                        backend::run_command(&backend, PlayerCommand::Seek(pos), &error_tx, player_tx.clone());
+                        // Update the position locally so the progress bar doesn't snap back while the player responds
+                        now_playing_resources.player_info.set_pos(pos);
+                        last_snapshot_time = now;
+                        continue;
+                    }
+
+                    // Drop the album that's being dragged in the queue view, moving its tracks in the queue as a block
+                    if let Some(drag) = queue_drag.take() {
+                        if let Some(entries) = queue_entries.as_mut().filter(|_| drag.to != drag.from) {
+                            let entry = entries.remove(drag.from);
+                            let ids = entry.ids();
+                            entries.insert(drag.to, entry);
+                            let before = entries.get(drag.to + 1).map(|next| next.tracks[0].1.clone());
+                            backend::move_in_queue(&backend, ids, before, &error_tx, queue_tx.clone());
+                        }
+                        continue;
+                    }
+
                     // Filter loc so that it's none if out of bounds
                     let loc = drag_placeholder_loc.filter(|loc| (loc[1]) < 3 && (loc[0]) < 3);
 
                     if let Some(mut u_dragged_item) = dragged_item.take() {
-                        if buttons["queue"].is_hovering(mouse_state.x(), mouse_state.y()) {
+                        // An album that was released close to where it was picked up was clicked, so it's put back
+                        // where it was and its details are shown
+                        let clicked = (x - drag_start_pos.x()).abs() <= CLICK_DISTANCE
+                            && (y - drag_start_pos.y()).abs() <= CLICK_DISTANCE;
+                        let loc = if clicked { Some(dragged_item_origin) } else { loc };
+
+                        let target_button = ["queue", "play_next"]
+                            .into_iter()
+                            .filter(|_| !clicked)
+                            .find(|name| buttons[name].is_hovering(mouse_state.x(), mouse_state.y()));
+                        if let Some(target_button) = target_button {
                             // Constants
-                            const START_SCALE: f32 =
-                                THUMBNAIL_SIZE_DRAG as f32 / THUMBNAIL_SIZE as f32;
-                            const START_OFFSET: f32 = THUMBNAIL_SIZE_DRAG as f32 / 2.0;
+                            let start_scale: f32 = thumbnail_size_drag as f32 / thumbnail_size as f32;
+                            let start_offset: f32 = thumbnail_size_drag as f32 / 2.0;
 
                             // The starting x and y positions of the thumbnail
                             let (x, y) = (
-                                dragged_item_pos[0] + START_OFFSET,
-                                dragged_item_pos[1] + START_OFFSET,
+                                dragged_item_pos[0] + start_offset,
+                                dragged_item_pos[1] + start_offset,
                             );
 
                             // Quadratic data for vertical animation curve
-                            const QUEUE_BUTTON_X: f32 = ARTWORK_SIZE as f32 - 20.0;
-                            const QUEUE_BUTTON_Y: f32 = ARTWORK_SIZE as f32 - 20.0;
+                            let queue_button_x: f32 = buttons[target_button].collision_rect.x as f32 + 16.0;
+                            let queue_button_y: f32 = artwork_size as f32 - 20.0;
                             const ANIM_ARC_HEIGHT: f32 = 150.0;
 
-                            let a = QUEUE_BUTTON_Y - y;
+                            let a = queue_button_y - y;
                             let b = -2.0 * (ANIM_ARC_HEIGHT - y);
                             let c = ANIM_ARC_HEIGHT - y;
                             let vertex_t = (-b + (b.powi(2) - 4.0 * a * c).sqrt()) / (2.0 * a);
@@ -495,102 +1041,469 @@ fn main() {
                             u_dragged_item.state = ItemState::Anim {
                                 duration: 0.5,
                                 current: 0.,
-                                x_curve: Box::new(move |t: f32| x + t * (QUEUE_BUTTON_X - x)),
+                                x_curve: Box::new(move |t: f32| x + t * (queue_button_x - x)),
                                 y_curve: Box::new(move |t: f32| {
                                     scale_y * (t - vertex_t).powi(2) + ANIM_ARC_HEIGHT
                                 }),
-                                scale_curve: Box::new(|t: f32| (1.0 - t) * START_SCALE),
+                                scale_curve: Box::new(move |t: f32| (1.0 - t) * start_scale),
                             };
-                            queueing_albums.push(u_dragged_item);
+                            queueing_albums.push((u_dragged_item, target_button));
 
                             // Freeze the next item in the third row until the animation finishes
-                            album_view_rows[2][2].state = ItemState::Freeze { timer: 0.8 };
+                            if let Some(item) = album_view_rows[2].get_mut(2) {
+                                item.state = ItemState::Freeze { timer: 0.8 };
+                            }
                             continue;
                         }
                         u_dragged_item.state = ItemState::Default;
                         if let Some(loc) = loc {
+                            let clicked_album = clicked.then(|| u_dragged_item.album.clone());
                             u_dragged_item.x_pos =
-                                AlbumViewItem::get_target_pos(loc[0], None) as f32;
-                            album_view_rows[loc[1]].insert(loc[0], u_dragged_item);
+                                AlbumViewItem::get_target_pos(loc[0], None, thumbnail_size) as f32;
+                            let row = &mut album_view_rows[loc[1]];
+                            row.insert(loc[0].min(row.len()), u_dragged_item);
+                            if let Some(album) = clicked_album {
+                                // Send the album that replaced the clicked one back to the shuffled albums
+                                if row.len() > 3 {
+                                    album_view_queue.add(row.pop().unwrap().album).unwrap();
+                                }
+                                backend::send_album_tracks_async(
+                                    &backend,
+                                    album.title().to_string(),
+                                    album.album_artist().to_string(),
+                                    album_tracks_tx.clone(),
+                                );
+                                album_detail = Some(AlbumDetail::new(album, &texture_creator));
+                                current_view = View::AlbumDetail;
+                                detail_scroll.reset();
+                            }
                         } else {
+                            // Albums that are dragged out of the grid are never picked by radio mode
+                            dismissed_albums.insert((
+                                u_dragged_item.album.title().to_string(),
+                                u_dragged_item.album.album_artist().to_string(),
+                            ));
+                            push_undo(
+                                &mut undo_stack,
+                                UndoAction::Dismiss {
+                                    album: u_dragged_item.album.clone(),
+                                    loc: dragged_item_origin,
+                                },
+                            );
                             album_view_queue.add(u_dragged_item.album).unwrap();
                         }
                         dragged_item = None;
                         continue;
                     }
 
-                    match Button::get_hovered_from_hash(&buttons, x, y) {
-                        Some(button_name) => match button_name {
-                            "heart_empty" => {
-                                osascript_requests::run_command(JXACommand::Love, player_tx.clone())
-                            }
-                            "heart_filled" => osascript_requests::run_command(
-                                JXACommand::Unlove,
-                                player_tx.clone(),
-                            ),
-                            "album_view" => current_view = View::AlbumSelect,
-                            "miniplayer_view" => current_view = View::Miniplayer,
-                            "play" | "pause" => osascript_requests::run_command(
-                                JXACommand::PlayPause,
-                                player_tx.clone(),
-                            ),
-                            "back_track" => osascript_requests::run_command(
-                                JXACommand::BackTrack,
-                                player_tx.clone(),
-                            ),
-                            "next_track" => osascript_requests::run_command(
-                                JXACommand::NextTrack,
-                                player_tx.clone(),
-                            ),
-                            "minimize" => {
-                                // TODO: temporary border enabling hack no longer necessary in Ventura, remove?
-                                canvas.window_mut().set_bordered(true);
-                                canvas.window_mut().minimize();
-                                canvas.window_mut().set_bordered(false);
+                    // Answer the restore prompt, replacing the tracks kept in the DJ queue with the last session's
+                    // albums if it was accepted, or clearing them if it wasn't
+                    if restore_prompt.is_some() {
+                        if restore_prompt_rects[1].contains_point((x, y)) {
+                            let albums = restore_prompt.take().unwrap();
+                            queued_albums = albums.clone();
+                            backend::restore_queue(&backend, albums, &error_tx);
+                            continue;
+                        }
+                        if restore_prompt_rects[2].contains_point((x, y)) {
+                            restore_prompt = None;
+                            queued_albums.clear();
+                            backend::reset_dj_playlist(&backend, &error_tx);
+                            continue;
+                        }
+                    }
+
+                    // Clicking the time readout switches between elapsed and remaining time
+                    if time_readout_rect.filter(|rect| rect.contains_point((x, y))).is_some() {
+                        show_remaining_time = !show_remaining_time;
+                        continue;
+                    }
+
+                    // Clicking the remove button of a row in the queue view removes its tracks from the queue
+                    if let Some((_, ids)) = queue_remove_target.as_ref().filter(|(rect, _)| rect.contains_point((x, y))) {
+                        backend::remove_from_queue(&backend, ids.clone(), &error_tx, queue_tx.clone());
+                        continue;
+                    }
+
+                    // Clicking the remove button of a row in the hidden albums view shows its album again
+                    if let Some((_, i)) = hidden_unhide_target.filter(|(rect, _)| rect.contains_point((x, y))) {
+                        let entry = hidden_entries.remove(i);
+                        hidden_albums.unhide(&entry.album.key());
+                        hidden_albums_changed = true;
+                        if AlbumFilter::allows(album_filter.as_ref(), &entry.album) {
+                            // Fill a gap left in the grid by hiding an album when no shuffled albums were left to
+                            // replace it. While searching, the grid holds the search results instead.
+                            let gap = album_view_rows.iter_mut().find(|row| row.len() < 3);
+                            if let Some(row) = gap.filter(|_| search.is_none()) {
+                                row.push(AlbumViewItem {
+                                    album: entry.album,
+                                    state: ItemState::Default,
+                                    x_pos: artwork_size as f32,
+                                    x_vel: 0.0,
+                                });
+                            } else {
+                                album_view_queue.add(entry.album).unwrap();
                             }
-                            "reshuffle" => {
-                                for row in album_view_rows.iter_mut() {
-                                    row.drain(..).for_each(|item| {
-                                        album_view_queue.add(item.album).unwrap();
-                                    });
+                        } else {
+                            filtered_out_albums.push(entry.album);
+                        }
+                        continue;
+                    }
+
+                    // Clicking play or queue in the album detail view plays or queues the whole album
+                    if let Some(detail) = album_detail.as_ref().filter(|_| current_view == View::AlbumDetail) {
+                        let (title, album_artist) =
+                            (detail.album.title().to_string(), detail.album.album_artist().to_string());
+                        if detail_action_rects[0].contains_point((x, y)) {
+                            remember_recent_album(&mut recent_albums, &title, &album_artist);
+                            remember_queued_album(&mut queued_albums, &title, &album_artist, true);
+                            backend::play_album_now(&backend, title, album_artist, &error_tx, player_tx.clone());
+                            current_view = View::Miniplayer;
+                            continue;
+                        }
+                        if detail_action_rects[1].contains_point((x, y)) {
+                            remember_recent_album(&mut recent_albums, &title, &album_artist);
+                            remember_queued_album(&mut queued_albums, &title, &album_artist, false);
+                            push_undo(
+                                &mut undo_stack,
+                                UndoAction::Queue {
+                                    title: title.clone(),
+                                    album_artist: album_artist.clone(),
+                                    play_next: false,
+                                },
+                            );
+                            backend::queue_album(&backend, title, album_artist, &error_tx);
+                            Toast::show(&mut toast, "album queued", &texture_creator);
+                            continue;
+                        }
+                    }
+
+                    // Clicking the queue button of a track in the album detail view queues just that track
+                    if let Some((_, id)) = detail_queue_target.as_ref().filter(|(rect, _)| rect.contains_point((x, y))) {
+                        backend::queue_tracks(&backend, vec![id.clone()], &error_tx);
+                        Toast::show(&mut toast, "track queued", &texture_creator);
+                        continue;
+                    }
+
+                    // Clicking an option in the filter chooser applies it and goes back to the album select screen
+                    if let Some(i) = hovered_filter_option.filter(|_| queue_list_rect.contains_point((x, y))) {
+                        album_filter = filter_options[i].0.clone();
+                        apply_filter(
+                            album_filter.as_ref(),
+                            |albums| shuffle::shuffle_albums(albums, config.weighted_shuffle, shuffle_time(), &mut rng),
+                            &mut album_view_rows,
+                            &mut album_view_queue,
+                            &mut filtered_out_albums,
+                            thumbnail_size,
+                            artwork_size,
+                        );
+                        current_view = View::AlbumSelect;
+                        let label = album_filter.as_ref().map_or(String::from("all albums"), AlbumFilter::label);
+                        Toast::show(&mut toast, &label, &texture_creator);
+                        continue;
+                    }
+
+                    pressed_button = Button::get_hovered_from_hash(&buttons, x, y);
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Z),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD | Mod::LGUIMOD | Mod::RGUIMOD) => {
+                    match undo_stack.pop() {
+                        // Take the album back out of the shuffled albums and slide it into the slot it was dragged
+                        // out of. If it's no longer there (e.g. it was reshuffled into the grid), there's nothing
+                        // to undo. During a search, it's only put back if it's still one of the matches.
+                        Some(UndoAction::Dismiss { album, loc }) => {
+                            let taken = take_from_queue(&mut album_view_queue, std::slice::from_ref(&album));
+                            if !taken.is_empty() {
+                                dismissed_albums.remove(&(album.title().to_string(), album.album_artist().to_string()));
+                                if !AlbumFilter::allows(album_filter.as_ref(), &album) {
+                                    filtered_out_albums.push(album);
+                                } else if search.as_ref().is_some_and(|search| !search.contains(&album)) {
+                                    album_view_queue.add(album).unwrap();
+                                } else {
+                                    return_to_grid(
+                                        &mut album_view_rows,
+                                        &mut album_view_queue,
+                                        album,
+                                        loc,
+                                        artwork_size,
+                                    );
                                 }
-                                for i in 0..9 {
-                                    if let Ok(a) = album_view_queue.remove() {
-                                        album_view_rows[i % 3].push(AlbumViewItem {
-                                            album: a,
-                                            x_pos: AlbumViewItem::get_target_pos(i / 3, None)
-                                                as f32
-                                                + ARTWORK_SIZE as f32,
-                                            x_vel: 0.0,
-                                            state: ItemState::Freeze {
-                                                timer: (i % 3 * 3 + i / 3) as f32 * 0.03,
-                                            },
-                                        })
-                                    } else {
-                                        break;
-                                    }
+                            }
+                        }
+                        // Show the album again, sliding it back into the slot it was hidden from unless the filter
+                        // now leaves it out. During a search, it's only added to the matches if it matches the query.
+                        Some(UndoAction::Hide { album, loc }) => {
+                            hidden_albums.unhide(&album.key());
+                            hidden_albums_changed = true;
+                            hidden_entries.retain(|entry| !Rc::ptr_eq(&entry.album, &album));
+                            if !AlbumFilter::allows(album_filter.as_ref(), &album) {
+                                filtered_out_albums.push(album);
+                            } else if let Some(u_search) = search.as_mut() {
+                                if u_search.insert_match(&album) {
+                                    u_search.update_text(&texture_creator);
+                                    return_to_grid(
+                                        &mut album_view_rows,
+                                        &mut album_view_queue,
+                                        album,
+                                        loc,
+                                        artwork_size,
+                                    );
+                                } else {
+                                    album_view_queue.add(album).unwrap();
                                 }
+                            } else {
+                                return_to_grid(&mut album_view_rows, &mut album_view_queue, album, loc, artwork_size);
                             }
-                            "close" => {
-                                // osascript_requests::remove_dj_playlist();
-                                break 'running;
+                        }
+                        Some(UndoAction::Queue {
+                            title,
+                            album_artist,
+                            play_next,
+                        }) => {
+                            let mut matches = queued_albums
+                                .iter()
+                                .enumerate()
+                                .filter(|(_, queued)| queued.album == title && queued.album_artist == album_artist);
+                            let queued = if play_next { matches.next() } else { matches.next_back() };
+                            if let Some((i, _)) = queued {
+                                queued_albums.remove(i);
                             }
-                            _ => {}
+                            backend::unqueue_album(&backend, title, album_artist, play_next, &error_tx);
+                            Toast::show(&mut toast, "removed from queue", &texture_creator);
+                        }
+                        None => {}
+                    }
+                }
+                // While searching, keys edit the query and page through the results instead of triggering shortcuts
+                Event::TextInput { text, .. } if search.is_some() && current_view == View::AlbumSelect => {
+                    let u_search = search.as_mut().unwrap();
+                    u_search.query.push_str(&text);
+                    u_search.update_matches(&library, &hidden_albums, album_filter.as_ref());
+                    search_page_changed = true;
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } if search.is_some() && current_view == View::AlbumSelect => {
+                    let u_search = search.as_mut().unwrap();
+                    match keycode {
+                        Keycode::Escape => pressed_button = Some("search"),
+                        Keycode::Backspace if u_search.query.pop().is_some() => {
+                            u_search.update_matches(&library, &hidden_albums, album_filter.as_ref());
+                            search_page_changed = true;
+                        }
+                        Keycode::Left if u_search.page > 0 => {
+                            u_search.page -= 1;
+                            search_page_changed = true;
+                        }
+                        Keycode::Right if u_search.page + 1 < u_search.page_count() => {
+                            u_search.page += 1;
+                            search_page_changed = true;
+                        }
+                        _ => {}
+                    }
+                }
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    // Keyboard shortcuts press the button that would perform the same action
+                    pressed_button = match keymap.get(&keycode) {
+                        Some(KeyAction::PlayPause) => Some("play"),
+                        Some(KeyAction::NextTrack) => Some("next_track"),
+                        Some(KeyAction::BackTrack) => Some("back_track"),
+                        Some(KeyAction::Love) if now_playing_resources.track_info.loved() => Some("heart_filled"),
+                        Some(KeyAction::Love) => Some("heart_empty"),
+                        Some(KeyAction::ToggleView) => match current_view {
+                            View::Miniplayer => Some("album_view"),
+                            View::AlbumSelect => Some("miniplayer_view"),
+                            View::Queue | View::Hidden | View::Filter | View::AlbumDetail => Some("album_view"),
                         },
-                        None => (),
+                        Some(KeyAction::Reshuffle) if current_view == View::AlbumSelect => Some("reshuffle"),
+                        Some(KeyAction::Search) if current_view == View::AlbumSelect => Some("search"),
+                        Some(KeyAction::Minimize) => Some("minimize"),
+                        _ => None,
+                    };
+                }
+                // Scrolling over the artwork in the miniplayer changes the volume, and scrolling elsewhere scrolls
+                // the list that's open
+                Event::MouseWheel { y, direction, .. } => {
+                    let y = if direction == MouseWheelDirection::Flipped { -y } else { y };
+                    match current_view {
+                        View::Miniplayer if artwork_rect.contains_point(mouse_state.pos()) => {
+                            volume_change += y * VOLUME_STEP;
+                        }
+                        View::Queue => queue_scroll.scroll(y),
+                        View::Hidden => hidden_scroll.scroll(y),
+                        View::Filter => filter_scroll.scroll(y),
+                        View::AlbumDetail => detail_scroll.scroll(y),
+                        _ => {}
                     }
                 }
                 Event::Window { win_event, .. } => {
                     match win_event {
                         WindowEvent::Moved { .. } => {
                             // Update the canvas scale in case the user drags the window to a different monitor
-                            engine::update_canvas_scale(&mut canvas, WINDOW_WIDTH, WINDOW_HEIGHT);
+                            engine::update_canvas_scale(&mut canvas, window_width, window_height);
                         }
                         _ => {}
                     }
                 }
                 _ => {}
             }
+
+            // Perform the action of the button that was clicked or pressed with a keyboard shortcut
+            match pressed_button {
+                Some(button_name) => match button_name {
+                    "heart_empty" => {
+                        backend::run_command(&backend, PlayerCommand::Love, &error_tx, player_tx.clone())
+                    }
+                    "heart_filled" => backend::run_command(
+                        &backend,
+                        PlayerCommand::Unlove,
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    "album_view" => {
+                        current_view = View::AlbumSelect;
+                        if !albums_loaded {
+                            Toast::show(&mut toast, "library still loading", &texture_creator);
+                        }
+                    }
+                    "miniplayer_view" => current_view = View::Miniplayer,
+                    // Clicking either queue box opens the queue view, which loads the queue straight away
+                    "queue" | "play_next" => {
+                        current_view = View::Queue;
+                        queue_scroll.reset();
+                        queue_refresh_timer = 0.;
+                    }
+                    "clear_queue" => backend::clear_queue(&backend, &error_tx, queue_tx.clone()),
+                    "hidden_albums" => {
+                        current_view = View::Hidden;
+                        hidden_scroll.reset();
+                    }
+                    "filter" => {
+                        current_view = View::Filter;
+                        filter_scroll.reset();
+                    }
+                    "play" | "pause" => backend::run_command(
+                        &backend,
+                        PlayerCommand::PlayPause,
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    "back_track" => backend::run_command(
+                        &backend,
+                        PlayerCommand::BackTrack,
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    "next_track" => backend::run_command(
+                        &backend,
+                        PlayerCommand::NextTrack,
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    "shuffle_off" | "shuffle_on" => backend::run_command(
+                        &backend,
+                        PlayerCommand::SetShuffle(button_name == "shuffle_off"),
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    // Cycle through the repeat modes in the same order as Apple Music
+                    "repeat_off" | "repeat_all" | "repeat_one" => backend::run_command(
+                        &backend,
+                        PlayerCommand::SetRepeat(match now_playing_resources.player_info.repeat() {
+                            RepeatMode::Off => RepeatMode::All,
+                            RepeatMode::All => RepeatMode::One,
+                            RepeatMode::One => RepeatMode::Off,
+                        }),
+                        &error_tx,
+                        player_tx.clone(),
+                    ),
+                    "minimize" => {
+                        // TODO: temporary border enabling hack no longer necessary in Ventura, remove?
+                        canvas.window_mut().set_bordered(true);
+                        canvas.window_mut().minimize();
+                        canvas.window_mut().set_bordered(false);
+                    }
+                    "reshuffle" if search.is_none() => refill_grid(
+                        &mut album_view_rows,
+                        &mut album_view_queue,
+                        |album_view_queue| (0..9).map_while(|_| album_view_queue.remove().ok()).collect(),
+                        thumbnail_size,
+                        artwork_size,
+                    ),
+                    // Start searching, remembering the albums in the grid so they can be put back afterwards
+                    "search" if search.is_none() && albums_loaded => {
+                        let mut u_search = Search {
+                            query: String::new(),
+                            matches: Vec::new(),
+                            page: 0,
+                            previous_grid: (0..9)
+                                .filter_map(|i| album_view_rows[i % 3].get(i / 3))
+                                .map(|item| item.album.clone())
+                                .collect(),
+                            text: None,
+                        };
+                        u_search.update_matches(&library, &hidden_albums, album_filter.as_ref());
+                        search = Some(u_search);
+                        text_input.start();
+                        search_page_changed = true;
+                    }
+                    "search" => search_ended = true,
+                    "close" => {
+                        // osascript_requests::remove_dj_playlist();
+                        break 'running;
+                    }
+                    _ => {}
+                },
+                None => (),
+            }
+        }
+
+        // End the search if it was cancelled or the album select view was closed, putting back the albums that were in
+        // the grid before it started. It carries on while an album's details are open from the results. Otherwise, show
+        // the current page of results if it changed.
+        let search_view = matches!(current_view, View::AlbumSelect | View::AlbumDetail);
+        if search.is_some() && (search_ended || !search_view) {
+            let previous_grid = search.take().unwrap().previous_grid;
+            text_input.stop();
+            refill_grid(
+                &mut album_view_rows,
+                &mut album_view_queue,
+                |album_view_queue| {
+                    let mut albums = take_from_queue(album_view_queue, &previous_grid);
+                    albums.extend((albums.len()..9).map_while(|_| album_view_queue.remove().ok()));
+                    albums
+                },
+                thumbnail_size,
+                artwork_size,
+            );
+        } else if let Some(u_search) = search.as_mut().filter(|_| search_page_changed) {
+            let page_albums = u_search.page_albums().to_vec();
+            refill_grid(
+                &mut album_view_rows,
+                &mut album_view_queue,
+                |album_view_queue| take_from_queue(album_view_queue, &page_albums),
+                thumbnail_size,
+                artwork_size,
+            );
+            u_search.update_text(&texture_creator);
+        }
+
+        // Save the hidden albums if any were hidden or shown again
+        if hidden_albums_changed {
+            hidden_albums_changed = false;
+            if let Err(e) = hidden_albums.save() {
+                println!("Unable to save hidden albums: {e}");
+                Toast::show(&mut toast, "couldn't save hidden albums", &texture_creator);
+            }
         }
 
         // Reset drag_in_progress if the mouse button was just lifted
@@ -602,32 +1515,217 @@ fn main() {
             window_interaction_in_progress = false;
         }
 
+        // Change the volume if the user scrolled over the artwork, updating it locally so the volume bar responds
+        // immediately. The player is sent the new volume rather than the change, so scrolling again before it
+        // responds doesn't apply a change twice.
+        if let Some(volume) = now_playing_resources.player_info.volume().filter(|_| volume_change != 0) {
+            let new_volume = (volume as i32 + volume_change).clamp(0, 100) as u8;
+            if new_volume != volume {
+                backend::run_command(&backend, PlayerCommand::SetVolume(new_volume), &error_tx, None);
+                now_playing_resources.player_info.set_volume(new_volume);
+            }
+            volume_bar_timer = VOLUME_BAR_DURATION;
+        }
+
+        // Follow the mouse while the seek bar is being dragged, showing the target time in the info area
+        if let Some(pos) = seek_preview.as_mut() {
+            let length = now_playing_resources.track_info.length();
+            *pos = seek_bar_pos(mouse_state.x(), length);
+            Toast::show(
+                &mut toast,
+                &format!("{} / {}", engine::format_time(*pos), engine::format_time(length)),
+                &texture_creator,
+            );
+        }
+
         // ALBUM AND PLAYER DATA ---------------------------------------------------------------------------------------
 
+        // When headless, wait for the player and album data before rendering the first frame so the output is
+        // deterministic
+        let wait_for_data = headless.is_some() && frame_count == 0;
+
         // If the now playing channel has new data in it, update the player and track data on this thread
-        if let Ok(response) = player_rx.try_recv() {
+        let player_response = if wait_for_data { player_rx.recv().ok() } else { player_rx.try_recv().ok() };
+        if let Some(response) = player_response {
+            player_reachable = response.is_ok();
+            if let Ok(Some(data)) = &response {
+                if data.player_info.state() != PlayerState::Stopped {
+                    dj_was_active = data.player_info.dj_active();
+                    remember_recent_album(&mut recent_albums, data.track_info.album(), data.track_info.album_artist());
+                }
+            }
+            // If the player couldn't be reached, show the placeholder until it can be
+            let response = response.unwrap_or_else(|e| {
+                println!("{e}");
+                Toast::show(&mut toast, e.player_data_summary(), &texture_creator);
+                None
+            });
             now_playing_resources.update(response, &texture_creator);
             // Update the last snapshot time, used to determine the player position when rendering
-            last_snapshot_time = Instant::now();
+            last_snapshot_time = now;
+        }
+
+        // Offer to restore the last session's queue once the DJ playlist has been created
+        if let Ok(albums) = restore_rx.try_recv() {
+            restore_prompt = Some(albums);
+        }
+
+        // Report any errors from requests to the music player
+        for e in error_rx.try_iter() {
+            println!("{}: {}", e.summary, e.error);
+            Toast::show(&mut toast, e.summary, &texture_creator);
+        }
+
+        // If the queue has been received, create the text for each of its rows. Only the latest response is used, since
+        // requests that were made earlier may have finished later.
+        if let Some(response) = queue_rx.try_iter().last() {
+            match response {
+                Ok(tracks) => {
+                    queued_albums = SavedAlbum::from_queue(&tracks);
+                    let mut entries = Vec::new();
+                    for album in QueuedAlbum::group(tracks) {
+                        let mut text = engine::text_to_texture(
+                            &format!("{} - {}", album.album, album.album_artist),
+                            &texture_creator,
+                            Color::RGB(255, 255, 255),
+                            Color::RGB(0, 0, 0),
+                        );
+                        text.set_blend_mode(BlendMode::Add);
+                        let mut entry = QueueEntry {
+                            album: (album.album.clone(), album.album_artist.clone()),
+                            text,
+                            tracks: Vec::new(),
+                        };
+                        for track in album.tracks {
+                            // Show the artist of tracks on compilations, where it differs from the album artist
+                            let title = if track.artist == album.album_artist {
+                                track.name
+                            } else {
+                                format!("{} - {}", track.name, track.artist)
+                            };
+                            let mut text = engine::text_to_texture(
+                                &title,
+                                &texture_creator,
+                                Color::RGB(255, 255, 255),
+                                Color::RGB(0, 0, 0),
+                            );
+                            text.set_blend_mode(BlendMode::Add);
+                            text.set_color_mod(icon_color_mod_default, icon_color_mod_default, icon_color_mod_default);
+                            entry.tracks.push((text, track.id));
+                        }
+                        entries.push(entry);
+                    }
+                    queue_entries = Some(entries);
+                    // The dragged album's index may have changed, so it's dropped back where it was
+                    queue_drag = None;
+                }
+                Err(e) => {
+                    println!("Unable to load queue: {e}");
+                    Toast::show(&mut toast, "couldn't load the queue", &texture_creator);
+                }
+            }
+        }
+
+        // If the tracks of the album in the detail view have been received, create the text for each of them. Tracks of
+        // albums whose details were closed before they arrived are ignored.
+        for ((title, album_artist), response) in album_tracks_rx.try_iter() {
+            let Some(detail) = album_detail
+                .as_mut()
+                .filter(|detail| detail.album.title() == title && detail.album.album_artist() == album_artist)
+            else {
+                continue;
+            };
+            let tracks = response.unwrap_or_else(|e| {
+                println!("Unable to load tracks: {e}");
+                Toast::show(&mut toast, "couldn't load tracks", &texture_creator);
+                Vec::new()
+            });
+            detail.set_tracks(tracks, &texture_creator, icon_color_mod_default);
+        }
+
+        // In radio mode, queue another album if there are only a few tracks left in the DJ queue. The shuffled albums
+        // are rotated through until one is found that hasn't been played recently or dismissed.
+        radio_cooldown = (radio_cooldown - dt).max(0.);
+        if let Some(response) = upcoming_rx.try_iter().last() {
+            if let Ok(tracks) = &response {
+                queued_albums = SavedAlbum::from_queue(tracks);
+            }
+            match response {
+                Ok(tracks)
+                    if config.radio
+                        && tracks.len() < config.radio_min_tracks as usize
+                        && radio_cooldown == 0.
+                        && dj_was_active =>
+                {
+                    for _ in 0..album_view_queue.size() {
+                        let album = album_view_queue.remove().unwrap();
+                        album_view_queue.add(album.clone()).unwrap();
+                        let key = (album.title().to_string(), album.album_artist().to_string());
+                        if recent_albums.contains(&key) || dismissed_albums.contains(&key) {
+                            continue;
+                        }
+                        remember_recent_album(&mut recent_albums, &key.0, &key.1);
+                        remember_queued_album(&mut queued_albums, &key.0, &key.1, false);
+                        backend::queue_album(&backend, key.0, key.1, &error_tx);
+                        radio_cooldown = RADIO_COOLDOWN;
+                        break;
+                    }
+                }
+                Err(e) => println!("Unable to check the queue: {e}"),
+                _ => {}
+            }
         }
 
         // If the base album resources are done loading, create and save their artwork textures
-        if let Ok(response) = album_rx.try_recv() {
-            let mut album_resources: Vec<AlbumResources> = response
+        let album_response = if wait_for_data { album_rx.recv().ok() } else { album_rx.try_recv().ok() };
+        if let Some(response) = album_response {
+            // If the library couldn't be loaded, leave the album select screen empty
+            let mut album_resources: Vec<Rc<AlbumResources>> = response
+                .unwrap_or_else(|e| {
+                    println!("Unable to load albums: {e}");
+                    Toast::show(&mut toast, "couldn't load library", &texture_creator);
+                    Vec::new()
+                })
                 .into_iter()
-                .map(|r| r.construct_artwork(&texture_creator))
+                .filter_map(|r| {
+                    r.construct_artwork(&texture_creator)
+                        .map_err(|e| println!("{e}"))
+                        .ok()
+                })
+                .map(Rc::new)
                 .collect();
 
-            album_resources.shuffle(&mut thread_rng());
+            albums_loaded = true;
+
+            shuffle::shuffle_albums(&mut album_resources, config.weighted_shuffle, shuffle_time(), &mut rng);
             album_resources.into_iter().for_each(|item| {
-                album_view_queue.add(Rc::new(item)).unwrap();
+                album_lookup.insert((item.title().to_string(), item.album_artist().to_string()), item.clone());
+                if hidden_albums.contains(&item.key()) {
+                    hidden_entries.push(HiddenEntry::new(item, &texture_creator));
+                } else if AlbumFilter::allows(album_filter.as_ref(), &item) {
+                    album_view_queue.add(item).unwrap();
+                } else {
+                    filtered_out_albums.push(item);
+                }
+            });
+            library = album_lookup.values().cloned().collect();
+            library.sort_by(|a, b| (a.album_artist(), a.title()).cmp(&(b.album_artist(), b.title())));
+            filter_options = std::iter::once(None)
+                .chain(AlbumFilter::options(library.iter().map(Rc::as_ref)).into_iter().map(Some))
+                .map(|option| {
+                    let label = option.as_ref().map_or(String::from("all albums"), AlbumFilter::label);
+                    (option, status_text_texture(&label))
+                })
+                .collect();
+            hidden_entries.sort_by(|a, b| {
+                (a.album.album_artist(), a.album.title()).cmp(&(b.album.album_artist(), b.album.title()))
             });
 
             for i in 0..9 {
                 if let Ok(a) = album_view_queue.remove() {
                     album_view_rows[i % 3].push(AlbumViewItem {
                         album: a,
-                        x_pos: AlbumViewItem::get_target_pos(i / 3, None) as f32,
+                        x_pos: AlbumViewItem::get_target_pos(i / 3, None, thumbnail_size) as f32,
                         x_vel: 0.0,
                         state: ItemState::Default,
                     })
@@ -646,6 +1744,11 @@ fn main() {
         for button in buttons.values_mut() {
             button.active = false;
         }
+        time_readout_rect = None;
+        queue_remove_target = None;
+        hidden_unhide_target = None;
+        hovered_filter_option = None;
+        detail_queue_target = None;
 
         match current_view {
             // DRAWING (ALBUM SELECT) ----------------------------------------------------------------------------------
@@ -655,8 +1758,8 @@ fn main() {
                 // The 3x3 grid coordinates of the album that's currently being hovered over
                 // Casts done individually to prevent negative positions rounding toward 0
                 let hovered_album_loc: [usize; 2] = [
-                    (mouse_state.x() as usize) / THUMBNAIL_SIZE as usize,
-                    (mouse_state.y() as usize) / THUMBNAIL_SIZE as usize,
+                    (mouse_state.x() as usize) / thumbnail_size as usize,
+                    (mouse_state.y() as usize) / thumbnail_size as usize,
                 ];
                 drag_placeholder_loc = dragged_item.as_ref().map(|_| hovered_album_loc);
 
@@ -667,14 +1770,14 @@ fn main() {
                             .filter(|loc| loc[1] == row_y)
                             .map(|loc| loc[0]);
                         let target_x =
-                            AlbumViewItem::get_target_pos(item_x, drag_placeholder_x) as f32;
-                        item.update(target_x);
+                            AlbumViewItem::get_target_pos(item_x, drag_placeholder_x, thumbnail_size) as f32;
+                        item.update(target_x, dt);
 
                         let thumbnail_rect = Rect::new(
                             item.x_pos as i32,
-                            I_THUMBNAIL_SIZE * row_y as i32,
-                            THUMBNAIL_SIZE,
-                            THUMBNAIL_SIZE,
+                            i_thumbnail_size * row_y as i32,
+                            thumbnail_size,
+                            thumbnail_size,
                         );
                         canvas
                             .copy(item.album.artwork(), None, thumbnail_rect)
@@ -688,8 +1791,8 @@ fn main() {
 
                     let target_pos = mouse_state.pos()
                         - Point::new(
-                            THUMBNAIL_SIZE_DRAG as i32 / 2,
-                            THUMBNAIL_SIZE_DRAG as i32 / 2,
+                            thumbnail_size_drag as i32 / 2,
+                            thumbnail_size_drag as i32 / 2,
                         );
                     let direction = [
                         target_pos.x() as f32 - dragged_item_pos[0],
@@ -701,14 +1804,14 @@ fn main() {
                     ];
 
                     dragged_item_pos = [
-                        dragged_item_pos[0] + velocity[0] * DT,
-                        dragged_item_pos[1] + velocity[1] * DT,
+                        dragged_item_pos[0] + velocity[0] * dt,
+                        dragged_item_pos[1] + velocity[1] * dt,
                     ];
                     let dragged_item_rect = Rect::new(
                         dragged_item_pos[0] as i32,
                         dragged_item_pos[1] as i32,
-                        THUMBNAIL_SIZE_DRAG,
-                        THUMBNAIL_SIZE_DRAG,
+                        thumbnail_size_drag,
+                        thumbnail_size_drag,
                     );
                     canvas.draw_shadowed(
                         &u_dragged_item.album.artwork(),
@@ -726,10 +1829,10 @@ fn main() {
                         if item.x_vel.abs() < 0.1 && matches!(item.state, ItemState::Default) {
                             let thumbnail_rect = Rect::new(
                                 item.x_pos as i32 - THUMBNAIL_SCALE_AMT_HOVER as i32 / 2,
-                                I_THUMBNAIL_SIZE * hovered_album_loc[1] as i32
+                                i_thumbnail_size * hovered_album_loc[1] as i32
                                     - THUMBNAIL_SCALE_AMT_HOVER as i32 / 2,
-                                THUMBNAIL_SIZE + THUMBNAIL_SCALE_AMT_HOVER,
-                                THUMBNAIL_SIZE + THUMBNAIL_SCALE_AMT_HOVER,
+                                thumbnail_size + THUMBNAIL_SCALE_AMT_HOVER,
+                                thumbnail_size + THUMBNAIL_SCALE_AMT_HOVER,
                             );
                             canvas.draw_shadowed(
                                 &item.album.artwork(),
@@ -746,41 +1849,42 @@ fn main() {
                 // GRADIENTS -------------------------------------------------------------------------------------------
 
                 // enable the top gradient when we pass a threshold
-                let enable_top_gradient = mouse_state.y() <= ENABLE_TOP_GRADIENT_THRESHOLD && mouse_state.y() >= 0
-                    && mouse_state.x() >= 0 && mouse_state.x() <= WINDOW_WIDTH as i32;
-                // enable the bottom gradient when we're hovering the queue button
+                let enable_top_gradient = mouse_state.y() <= enable_top_gradient_threshold && mouse_state.y() >= 0
+                    && mouse_state.x() >= 0 && mouse_state.x() <= window_width as i32;
+                // enable the bottom gradient when we're hovering the queue or play next buttons
                 let enable_bottom_gradient = 
-                    mouse_state.y() >= ENABLE_BOTTOM_GRADIENT_THRESHOLD && mouse_state.y() <= ARTWORK_SIZE as i32 && 
-                    mouse_state.x() >= ENABLE_BOTTOM_GRADIENT_THRESHOLD && mouse_state.x() <= ARTWORK_SIZE as i32;
+                    mouse_state.y() >= enable_bottom_gradient_threshold && mouse_state.y() <= artwork_size as i32 && 
+                    mouse_state.x() >= enable_bottom_gradient_x_threshold && mouse_state.x() <= artwork_size as i32;
 
-                let top_alpha_delta = if enable_top_gradient { GRADIENT_MAX_ALPHA } else { 0 } as i16
+                let top_alpha_delta = if enable_top_gradient { gradient_max_alpha } else { 0 } as i16
                     - top_gradient_alpha as i16;
-                top_gradient_alpha = (top_gradient_alpha as f32 + top_alpha_delta as f32 * GRADIENT_ALPHA_SPEED * DT) as u8;
+                top_gradient_alpha = (top_gradient_alpha as f32 + top_alpha_delta as f32 * GRADIENT_ALPHA_SPEED * dt) as u8;
 
-                let bottom_alpha_delta = if enable_bottom_gradient { GRADIENT_MAX_ALPHA } else { 0 } as i16
+                let bottom_alpha_delta = if enable_bottom_gradient { gradient_max_alpha } else { 0 } as i16
                     - bottom_gradient_alpha as i16;
-                bottom_gradient_alpha = (bottom_gradient_alpha as f32 + bottom_alpha_delta as f32 * GRADIENT_ALPHA_SPEED * DT) as u8;
+                bottom_gradient_alpha = (bottom_gradient_alpha as f32 + bottom_alpha_delta as f32 * GRADIENT_ALPHA_SPEED * dt) as u8;
 
                 gradient.set_alpha_mod(top_gradient_alpha);
                 canvas.copy(&gradient, None, top_gradient_rect).unwrap();
                 gradient.set_alpha_mod(bottom_gradient_alpha);
                 canvas.copy_ex(&gradient, None, bottom_gradient_rect, 0., None, false, true).unwrap();
 
-                // QUEUE BUTTON ----------------------------------------------------------------------------------------
+                // QUEUE BUTTONS ---------------------------------------------------------------------------------------
 
-                buttons.get_mut("queue").unwrap().texture_default =
-                    &icon_textures_default["queue_closed.png"];
-                buttons.get_mut("queue").unwrap().texture_hover =
-                    &icon_textures_hover["queue_open.png"];
-                buttons.get_mut("queue").unwrap().texture_pressed =
-                    &icon_textures_hover["queue_open.png"];
+                for name in ["queue", "play_next"] {
+                    let button = buttons.get_mut(name).unwrap();
+                    button.texture_default = &icon_textures_default[&format!("{name}_closed.png")];
+                    button.texture_hover = &icon_textures_hover[&format!("{name}_open.png")];
+                    button.texture_pressed = &icon_textures_hover[&format!("{name}_open.png")];
+                }
 
                 for i in 0..queueing_albums.len() {
-                    buttons.get_mut("queue").unwrap().texture_default =
-                        &icon_textures_default["queue_open.png"];
-                    queueing_albums[i].update(0.0);
-                    if let Some((pos, scale)) = queueing_albums[i].get_anim_pos_and_scale() {
-                        let scale = (THUMBNAIL_SIZE as f32 * scale) as u32;
+                    let target_button = queueing_albums[i].1;
+                    buttons.get_mut(target_button).unwrap().texture_default =
+                        &icon_textures_default[&format!("{target_button}_open.png")];
+                    queueing_albums[i].0.update(0.0, dt);
+                    if let Some((pos, scale)) = queueing_albums[i].0.get_anim_pos_and_scale() {
+                        let scale = (thumbnail_size as f32 * scale) as u32;
                         let thumbnail_rect = Rect::new(
                             pos[0] as i32 - scale as i32 / 2,
                             pos[1] as i32 - scale as i32 / 2,
@@ -788,15 +1892,28 @@ fn main() {
                             scale,
                         );
                         canvas
-                            .copy(queueing_albums[i].album.artwork(), None, thumbnail_rect)
+                            .copy(queueing_albums[i].0.album.artwork(), None, thumbnail_rect)
                             .unwrap();
                     } else {
-                        queue_button_squash_frame = 2;
-                        let album = queueing_albums.remove(i).album;
-                        osascript_requests::queue_album(
-                            album.title().to_string(),
-                            album.album_artist().to_string(),
+                        let album = queueing_albums.remove(i).0.album;
+                        let (title, album_artist) = (album.title().to_string(), album.album_artist().to_string());
+                        remember_recent_album(&mut recent_albums, &title, &album_artist);
+                        remember_queued_album(&mut queued_albums, &title, &album_artist, target_button == "play_next");
+                        push_undo(
+                            &mut undo_stack,
+                            UndoAction::Queue {
+                                title: title.clone(),
+                                album_artist: album_artist.clone(),
+                                play_next: target_button == "play_next",
+                            },
                         );
+                        if target_button == "play_next" {
+                            play_next_button_squash_frame = 2;
+                            backend::play_album_next(&backend, title, album_artist, &error_tx);
+                        } else {
+                            queue_button_squash_frame = 2;
+                            backend::queue_album(&backend, title, album_artist, &error_tx);
+                        }
                         album_view_queue.add(album).unwrap();
                     }
                 }
@@ -804,31 +1921,27 @@ fn main() {
                 buttons.get_mut("minimize").unwrap().active = true;
                 buttons.get_mut("close").unwrap().active = true;
                 buttons.get_mut("miniplayer_view").unwrap().active = true;
-                buttons.get_mut("reshuffle").unwrap().active = true;
+                buttons.get_mut("reshuffle").unwrap().active = search.is_none();
+                buttons.get_mut("hidden_albums").unwrap().active = true;
+                buttons.get_mut("search").unwrap().active = true;
+                buttons.get_mut("filter").unwrap().active = true;
 
                 buttons.get_mut("queue").unwrap().active = true;
-
-                // Squash animation for the queue button after an album is queued
-                match queue_button_squash_frame {
-                    2 => {
-                        buttons.get_mut("queue").unwrap().texture_default =
-                            &icon_textures_default["queue_squashed_2.png"];
-                        buttons.get_mut("queue").unwrap().texture_hover =
-                            &icon_textures_hover["queue_squashed_2.png"];
-                        buttons.get_mut("queue").unwrap().texture_pressed =
-                            &icon_textures_hover["queue_squashed_2.png"];
-                        queue_button_squash_frame -= 1;
-                    }
-                    1 => {
-                        buttons.get_mut("queue").unwrap().texture_default =
-                            &icon_textures_default["queue_squashed_1.png"];
-                        buttons.get_mut("queue").unwrap().texture_hover =
-                            &icon_textures_hover["queue_squashed_1.png"];
-                        buttons.get_mut("queue").unwrap().texture_pressed =
-                            &icon_textures_hover["queue_squashed_1.png"];
-                        queue_button_squash_frame -= 1;
+                buttons.get_mut("play_next").unwrap().active = true;
+
+                // Squash animation for each queue button after an album is queued with it
+                for (name, squash_frame) in [
+                    ("queue", &mut queue_button_squash_frame),
+                    ("play_next", &mut play_next_button_squash_frame),
+                ] {
+                    if *squash_frame > 0 {
+                        let filename = format!("{name}_squashed_{squash_frame}.png");
+                        let button = buttons.get_mut(name).unwrap();
+                        button.texture_default = &icon_textures_default[&filename];
+                        button.texture_hover = &icon_textures_hover[&filename];
+                        button.texture_pressed = &icon_textures_hover[&filename];
+                        *squash_frame -= 1;
                     }
-                    _ => {}
                 }
 
                 // Fill the info rect again to prevent artworks from hanging over
@@ -836,19 +1949,373 @@ fn main() {
                 canvas
                     .fill_rect(Rect::new(
                         0,
-                        ARTWORK_SIZE as i32,
-                        ARTWORK_SIZE,
-                        INFO_AREA_HEIGHT,
+                        artwork_size as i32,
+                        artwork_size,
+                        info_area_height,
                     ))
                     .unwrap();
             }
 
+            // DRAWING (QUEUE) -----------------------------------------------------------------------------------------
+            View::Queue => {
+                // Keep the queue up to date while it's open, since tracks are removed from it as they finish playing.
+                // It isn't refreshed while an album is being dragged, since that would drop the album.
+                if queue_drag.is_none() {
+                    queue_refresh_timer -= dt;
+                }
+                if queue_refresh_timer <= 0. {
+                    backend::send_queue_async(&backend, queue_tx.clone());
+                    queue_refresh_timer = QUEUE_REFRESH_INTERVAL;
+                }
+
+                match queue_entries.as_ref() {
+                    Some(entries) if !entries.is_empty() => {
+                        // The dragged album is taken out of the list, and only its header row follows the mouse
+                        let dragged_i = queue_drag.as_ref().map(|drag| drag.from);
+                        let listed: Vec<usize> = (0..entries.len()).filter(|i| Some(*i) != dragged_i).collect();
+
+                        // The dragged album will be dropped before the first album that's centered below its header
+                        if let Some(drag) = queue_drag.as_mut() {
+                            let header_center = mouse_state.y() - drag.grab_offset + QueueEntry::HEADER_HEIGHT / 2;
+                            let mut entry_y = queue_list_rect.y() - queue_scroll.pos();
+                            drag.to = listed.len();
+                            for (list_i, i) in listed.iter().enumerate() {
+                                if header_center < entry_y + entries[*i].height() / 2 {
+                                    drag.to = list_i;
+                                    break;
+                                }
+                                entry_y += entries[*i].height();
+                            }
+                        }
+                        let drop_loc = queue_drag.as_ref().map(|drag| drag.to);
+
+                        // Keep the scroll position within the bounds of the list, including the gap left for the
+                        // dragged album
+                        let content_height: i32 = listed.iter().map(|i| entries[*i].height()).sum::<i32>()
+                            + if drop_loc.is_some() { QueueEntry::HEADER_HEIGHT } else { 0 };
+                        let queue_scroll_pos = queue_scroll.clamp(content_height, queue_list_rect.height());
+
+                        // Lay out the rows as (rect, album index, track index), where header rows have no track index
+                        let mut rows: Vec<(Rect, usize, Option<usize>)> = Vec::new();
+                        let mut row_y = queue_list_rect.y() - queue_scroll_pos;
+                        for (list_i, i) in listed.iter().enumerate() {
+                            if drop_loc == Some(list_i) {
+                                row_y += QueueEntry::HEADER_HEIGHT;
+                            }
+                            rows.push((Rect::new(0, row_y, artwork_size, QueueEntry::HEADER_HEIGHT as u32), *i, None));
+                            row_y += QueueEntry::HEADER_HEIGHT;
+                            for track_i in 0..entries[*i].tracks.len() {
+                                rows.push((
+                                    Rect::new(0, row_y, artwork_size, QueueEntry::TRACK_HEIGHT as u32),
+                                    *i,
+                                    Some(track_i),
+                                ));
+                                row_y += QueueEntry::TRACK_HEIGHT;
+                            }
+                        }
+
+                        canvas.set_clip_rect(queue_list_rect);
+                        for (row_rect, i, track_i) in rows {
+                            if !row_rect.has_intersection(queue_list_rect) {
+                                continue;
+                            }
+                            let entry = &entries[i];
+
+                            // Highlight the hovered row
+                            let hovered = queue_drag.is_none()
+                                && window_input_focus
+                                && queue_list_rect.contains_point(mouse_state.pos())
+                                && row_rect.contains_point(mouse_state.pos());
+                            if hovered {
+                                canvas.set_blend_mode(BlendMode::None);
+                                canvas.set_draw_color(Color::RGB(30, 30, 30));
+                                canvas.fill_rect(row_rect).unwrap();
+                            }
+
+                            // Draw the text, cutting it off before the remove button
+                            let text = match track_i {
+                                Some(track_i) => &entry.tracks[track_i].0,
+                                None => {
+                                    let artwork = album_lookup.get(&entry.album).map(|album| album.artwork());
+                                    draw_queue_artwork(&mut canvas, artwork, row_rect);
+                                    &entry.text
+                                }
+                            };
+                            draw_queue_text(&mut canvas, text, row_rect, artwork_size - 52);
+
+                            // Draw the remove button on the hovered row
+                            if hovered {
+                                let remove_rect = draw_row_button(
+                                    &mut canvas,
+                                    &icon_textures_default["remove.png"],
+                                    &icon_textures_hover["remove.png"],
+                                    row_rect,
+                                    mouse_state.pos(),
+                                );
+                                let ids = match track_i {
+                                    Some(track_i) => vec![entry.tracks[track_i].1.clone()],
+                                    None => entry.ids(),
+                                };
+                                queue_remove_target = Some((remove_rect, ids));
+                            }
+                        }
+
+                        // Draw the header row of the album that's being dragged under the mouse
+                        if let Some(drag) = queue_drag.as_ref() {
+                            let entry = &entries[drag.from];
+                            let row_rect = Rect::new(
+                                0,
+                                mouse_state.y() - drag.grab_offset,
+                                artwork_size,
+                                QueueEntry::HEADER_HEIGHT as u32,
+                            );
+                            canvas.set_blend_mode(BlendMode::None);
+                            canvas.set_draw_color(Color::RGB(30, 30, 30));
+                            canvas.fill_rect(row_rect).unwrap();
+                            let artwork = album_lookup.get(&entry.album).map(|album| album.artwork());
+                            draw_queue_artwork(&mut canvas, artwork, row_rect);
+                            draw_queue_text(&mut canvas, &entry.text, row_rect, artwork_size - 52);
+                        }
+                        canvas.set_clip_rect(None);
+
+                        buttons.get_mut("clear_queue").unwrap().active = true;
+                    }
+                    // Show whether the queue is empty or still loading
+                    entries => {
+                        let texture = if entries.is_some() { &queue_empty_texture } else { &queue_loading_texture };
+                        let query = texture.query();
+                        engine::copy_unscaled(
+                            texture,
+                            (artwork_size as i32 - query.width as i32) / 2,
+                            queue_list_rect.center().y() - query.height as i32 / 2,
+                            &mut canvas,
+                        )
+                        .unwrap();
+                    }
+                }
+
+                buttons.get_mut("album_view").unwrap().active = true;
+                buttons.get_mut("minimize").unwrap().active = true;
+                buttons.get_mut("close").unwrap().active = true;
+            }
+
+            // DRAWING (HIDDEN ALBUMS) ---------------------------------------------------------------------------------
+            View::Hidden => {
+                if hidden_entries.is_empty() {
+                    let query = hidden_empty_texture.query();
+                    engine::copy_unscaled(
+                        &hidden_empty_texture,
+                        (artwork_size as i32 - query.width as i32) / 2,
+                        queue_list_rect.center().y() - query.height as i32 / 2,
+                        &mut canvas,
+                    )
+                    .unwrap();
+                } else {
+                    let content_height = hidden_entries.len() as i32 * QueueEntry::HEADER_HEIGHT;
+                    let hidden_scroll_pos = hidden_scroll.clamp(content_height, queue_list_rect.height());
+
+                    // Each hidden album is shown like an album's header row in the queue view
+                    canvas.set_clip_rect(queue_list_rect);
+                    for (i, entry) in hidden_entries.iter().enumerate() {
+                        let row_rect = Rect::new(
+                            0,
+                            queue_list_rect.y() - hidden_scroll_pos + i as i32 * QueueEntry::HEADER_HEIGHT,
+                            artwork_size,
+                            QueueEntry::HEADER_HEIGHT as u32,
+                        );
+                        if !row_rect.has_intersection(queue_list_rect) {
+                            continue;
+                        }
+
+                        let hovered = window_input_focus
+                            && queue_list_rect.contains_point(mouse_state.pos())
+                            && row_rect.contains_point(mouse_state.pos());
+                        if hovered {
+                            canvas.set_blend_mode(BlendMode::None);
+                            canvas.set_draw_color(Color::RGB(30, 30, 30));
+                            canvas.fill_rect(row_rect).unwrap();
+                        }
+                        draw_queue_artwork(&mut canvas, Some(entry.album.artwork()), row_rect);
+                        draw_queue_text(&mut canvas, &entry.text, row_rect, artwork_size - 52);
+
+                        // The remove button on the hovered row shows the album again
+                        if hovered {
+                            let remove_rect = draw_row_button(
+                                &mut canvas,
+                                &icon_textures_default["remove.png"],
+                                &icon_textures_hover["remove.png"],
+                                row_rect,
+                                mouse_state.pos(),
+                            );
+                            hidden_unhide_target = Some((remove_rect, i));
+                        }
+                    }
+                    canvas.set_clip_rect(None);
+                }
+
+                buttons.get_mut("album_view").unwrap().active = true;
+                buttons.get_mut("minimize").unwrap().active = true;
+                buttons.get_mut("close").unwrap().active = true;
+            }
+
+            // DRAWING (FILTER) ----------------------------------------------------------------------------------------
+            View::Filter => {
+                let content_height = filter_options.len() as i32 * FILTER_OPTION_HEIGHT;
+                let filter_scroll_pos = filter_scroll.clamp(content_height, queue_list_rect.height());
+
+                // The filter that's in use is brightened
+                canvas.set_clip_rect(queue_list_rect);
+                for (i, (option, text)) in filter_options.iter_mut().enumerate() {
+                    let row_rect = Rect::new(
+                        0,
+                        queue_list_rect.y() - filter_scroll_pos + i as i32 * FILTER_OPTION_HEIGHT,
+                        artwork_size,
+                        FILTER_OPTION_HEIGHT as u32,
+                    );
+                    if !row_rect.has_intersection(queue_list_rect) {
+                        continue;
+                    }
+
+                    if window_input_focus
+                        && queue_list_rect.contains_point(mouse_state.pos())
+                        && row_rect.contains_point(mouse_state.pos())
+                    {
+                        canvas.set_blend_mode(BlendMode::None);
+                        canvas.set_draw_color(Color::RGB(30, 30, 30));
+                        canvas.fill_rect(row_rect).unwrap();
+                        hovered_filter_option = Some(i);
+                    }
+                    let color_mod =
+                        if *option == album_filter { icon_color_mod_hover } else { icon_color_mod_default };
+                    text.set_color_mod(color_mod, color_mod, color_mod);
+                    let query = text.query();
+                    engine::copy_unscaled(
+                        text,
+                        INFO_PADDING as i32,
+                        row_rect.y() + (FILTER_OPTION_HEIGHT - query.height as i32) / 2,
+                        &mut canvas,
+                    )
+                    .unwrap();
+                }
+                canvas.set_clip_rect(None);
+
+                buttons.get_mut("album_view").unwrap().active = true;
+                buttons.get_mut("minimize").unwrap().active = true;
+                buttons.get_mut("close").unwrap().active = true;
+            }
+
+            // DRAWING (ALBUM DETAIL) ----------------------------------------------------------------------------------
+            View::AlbumDetail => {
+                if let Some(detail) = album_detail.as_ref() {
+                    canvas.copy(detail.album.artwork(), None, detail_artwork_rect).unwrap();
+
+                    // Draw the album's details, cutting them off at the edge of the window
+                    let max_width = artwork_size - DETAIL_TEXT_X as u32 - 6;
+                    for (i, line) in detail.lines.iter().enumerate() {
+                        let query = line.query();
+                        let width = query.width.min(max_width);
+                        canvas
+                            .copy(
+                                line,
+                                Rect::new(0, 0, width, query.height),
+                                Rect::new(DETAIL_TEXT_X, detail_artwork_rect.y() + i as i32 * 16, width, query.height),
+                            )
+                            .unwrap();
+                    }
+
+                    // Draw the play and queue options, brightening the one that's hovered
+                    for (texture, rect) in detail_action_textures.iter_mut().zip(detail_action_rects) {
+                        let color_mod = if rect.contains_point(mouse_state.pos()) {
+                            icon_color_mod_hover
+                        } else {
+                            icon_color_mod_default
+                        };
+                        texture.set_color_mod(color_mod, color_mod, color_mod);
+                        canvas.copy(texture, None, rect).unwrap();
+                    }
+
+                    match detail.tracks.as_ref() {
+                        Some(tracks) if !tracks.is_empty() => {
+                            let content_height = tracks.len() as i32 * AlbumDetail::TRACK_HEIGHT;
+                            let detail_scroll_pos = detail_scroll.clamp(content_height, detail_list_rect.height());
+
+                            canvas.set_clip_rect(detail_list_rect);
+                            for (i, track) in tracks.iter().enumerate() {
+                                let row_rect = Rect::new(
+                                    0,
+                                    detail_list_rect.y() - detail_scroll_pos + i as i32 * AlbumDetail::TRACK_HEIGHT,
+                                    artwork_size,
+                                    AlbumDetail::TRACK_HEIGHT as u32,
+                                );
+                                if !row_rect.has_intersection(detail_list_rect) {
+                                    continue;
+                                }
+
+                                let hovered = window_input_focus
+                                    && detail_list_rect.contains_point(mouse_state.pos())
+                                    && row_rect.contains_point(mouse_state.pos());
+                                if hovered {
+                                    canvas.set_blend_mode(BlendMode::None);
+                                    canvas.set_draw_color(Color::RGB(30, 30, 30));
+                                    canvas.fill_rect(row_rect).unwrap();
+                                }
+
+                                // The length is right-aligned before the queue button, and the title is cut off
+                                // before the length
+                                let length_query = track.length.query();
+                                let length_x = row_rect.right() - 22 - length_query.width as i32;
+                                let text_y = row_rect.y() + (row_rect.height() as i32 - length_query.height as i32) / 2;
+                                engine::copy_unscaled(&track.length, length_x, text_y, &mut canvas).unwrap();
+                                let query = track.text.query();
+                                let width = query.width.min((length_x - 14).max(0) as u32);
+                                canvas
+                                    .copy(
+                                        &track.text,
+                                        Rect::new(0, 0, width, query.height),
+                                        Rect::new(6, text_y, width, query.height),
+                                    )
+                                    .unwrap();
+
+                                if hovered {
+                                    let queue_rect = draw_row_button(
+                                        &mut canvas,
+                                        &icon_textures_default["add.png"],
+                                        &icon_textures_hover["add.png"],
+                                        row_rect,
+                                        mouse_state.pos(),
+                                    );
+                                    detail_queue_target = Some((queue_rect, track.id.clone()));
+                                }
+                            }
+                            canvas.set_clip_rect(None);
+                        }
+                        // Show whether the album has no tracks or they're still loading
+                        tracks => {
+                            let texture =
+                                if tracks.is_some() { &tracks_empty_texture } else { &tracks_loading_texture };
+                            let query = texture.query();
+                            engine::copy_unscaled(
+                                texture,
+                                (artwork_size as i32 - query.width as i32) / 2,
+                                detail_list_rect.center().y() - query.height as i32 / 2,
+                                &mut canvas,
+                            )
+                            .unwrap();
+                        }
+                    }
+                }
+
+                buttons.get_mut("album_view").unwrap().active = true;
+                buttons.get_mut("minimize").unwrap().active = true;
+                buttons.get_mut("close").unwrap().active = true;
+            }
+
             // DRAWING (MINIPLAYER) ------------------------------------------------------------------------------------
             View::Miniplayer => {
                 // Draw the album art
                 let art_tex = now_playing_resources.track_resources.artwork_texture();
                 canvas
-                    .copy(art_tex, None, Rect::new(0, 0, ARTWORK_SIZE, ARTWORK_SIZE))
+                    .copy(art_tex, None, Rect::new(0, 0, artwork_size, artwork_size))
                     .unwrap();
 
                 //Draw an overlay if the user is hovering over the window
@@ -859,30 +2326,87 @@ fn main() {
                     canvas.set_blend_mode(BlendMode::Mod);
                     canvas.set_draw_color(Color::RGB(120, 120, 120));
                     canvas
-                        .fill_rect(Rect::new(0, 0, ARTWORK_SIZE, ARTWORK_SIZE))
+                        .fill_rect(Rect::new(0, 0, artwork_size, artwork_size))
                         .unwrap();
 
-                    // Draw a progress bar
+                    // Draw a progress bar, which is enlarged when it's hovered to show that it can be used to seek
+                    let seek_bar_active = seek_preview.is_some()
+                        || seek_rect.contains_point(mouse_state.pos())
+                            && now_playing_resources.player_info.state() != PlayerState::Stopped;
+                    let progress_bar_height = if seek_bar_active { 3 } else { 1 };
                     canvas.set_blend_mode(BlendMode::Add);
+                    if seek_bar_active {
+                        canvas.set_draw_color(Color::RGB(40, 40, 40));
+                        canvas
+                            .fill_rect(Rect::new(
+                                0,
+                                (artwork_size - progress_bar_height) as i32,
+                                artwork_size,
+                                progress_bar_height,
+                            ))
+                            .unwrap();
+                    }
                     canvas.set_draw_color(Color::RGB(100, 100, 100));
 
-                    let percent_elapsed = (now_playing_resources.player_info.pos()
-                        + if now_playing_resources.player_info.state() == PlayerState::Playing {
-                            last_snapshot_time.elapsed().as_secs_f64()
-                        } else {
-                            0.
-                        })
-                        / now_playing_resources.track_info.length();
+                    // While seeking, show the position that will be seeked to instead of the current one
+                    let pos = seek_preview.unwrap_or_else(|| {
+                        now_playing_resources.player_info.pos()
+                            + if now_playing_resources.player_info.state() == PlayerState::Playing {
+                                now - last_snapshot_time
+                            } else {
+                                0.
+                            }
+                    });
+                    let percent_elapsed = (pos / now_playing_resources.track_info.length()).min(1.);
 
-                    canvas
-                        .draw_line(
-                            Point::new(0, ARTWORK_SIZE as i32 - 1),
-                            Point::new(
-                                (ARTWORK_SIZE as f64 * percent_elapsed) as i32,
-                                ARTWORK_SIZE as i32 - 1,
-                            ),
-                        )
-                        .unwrap();
+                    let progress_width = (artwork_size as f64 * percent_elapsed) as u32;
+                    if progress_width > 0 {
+                        canvas
+                            .fill_rect(Rect::new(
+                                0,
+                                (artwork_size - progress_bar_height) as i32,
+                                progress_width,
+                                progress_bar_height,
+                            ))
+                            .unwrap();
+                    }
+
+                    // Draw the elapsed or remaining time
+                    if config.show_time_readout && now_playing_resources.player_info.state() != PlayerState::Stopped {
+                        let length = now_playing_resources.track_info.length();
+                        let text = if show_remaining_time {
+                            format!("-{}", engine::format_time(length - pos))
+                        } else {
+                            format!("{} / {}", engine::format_time(pos), engine::format_time(length))
+                        };
+                        if !matches!(&time_readout, Some((old_text, _)) if *old_text == text) {
+                            let mut texture = engine::text_to_texture(
+                                &text,
+                                &texture_creator,
+                                Color::RGB(255, 255, 255),
+                                Color::RGB(0, 0, 0),
+                            );
+                            texture.set_blend_mode(BlendMode::Add);
+                            time_readout = Some((text, texture));
+                        }
+                        let (_, texture) = time_readout.as_mut().unwrap();
+
+                        let query = texture.query();
+                        let rect = Rect::new(
+                            (artwork_size as i32 - query.width as i32) / 2,
+                            artwork_size as i32 - 40,
+                            query.width,
+                            query.height,
+                        );
+                        let color_mod = if rect.contains_point(mouse_state.pos()) {
+                            icon_color_mod_hover
+                        } else {
+                            icon_color_mod_default
+                        };
+                        texture.set_color_mod(color_mod, color_mod, color_mod);
+                        canvas.copy(texture, None, rect).unwrap();
+                        time_readout_rect = Some(rect);
+                    }
 
                     // Update button visibility based on new data
                     buttons.get_mut("next_track").unwrap().active = true;
@@ -902,6 +2426,41 @@ fn main() {
                         now_playing_resources.player_info.state() != PlayerState::Playing;
                     buttons.get_mut("pause").unwrap().active =
                         now_playing_resources.player_info.state() == PlayerState::Playing;
+
+                    let shuffle = now_playing_resources.player_info.shuffle();
+                    buttons.get_mut("shuffle_off").unwrap().active = !shuffle;
+                    buttons.get_mut("shuffle_on").unwrap().active = shuffle;
+
+                    let repeat = now_playing_resources.player_info.repeat();
+                    buttons.get_mut("repeat_off").unwrap().active = repeat == RepeatMode::Off;
+                    buttons.get_mut("repeat_all").unwrap().active = repeat == RepeatMode::All;
+                    buttons.get_mut("repeat_one").unwrap().active = repeat == RepeatMode::One;
+                }
+
+                // VOLUME BAR ------------------------------------------------------------------------------------------
+
+                // Fade the volume bar in after scrolling, then out again once the timer runs out
+                volume_bar_timer = (volume_bar_timer - dt).max(0.);
+                let volume_alpha_delta = if volume_bar_timer > 0. { VOLUME_BAR_MAX_ALPHA } else { 0 } as i16
+                    - volume_bar_alpha as i16;
+                volume_bar_alpha = (volume_bar_alpha as f32 + volume_alpha_delta as f32 * GRADIENT_ALPHA_SPEED * dt) as u8;
+
+                if let Some(volume) = now_playing_resources.player_info.volume().filter(|_| volume_bar_alpha > 0) {
+                    let filled_height = volume_bar_rect.height() * volume as u32 / 100;
+                    canvas.set_blend_mode(BlendMode::Blend);
+                    canvas.set_draw_color(Color::RGBA(0, 0, 0, volume_bar_alpha / 2));
+                    canvas.fill_rect(volume_bar_rect).unwrap();
+                    if filled_height > 0 {
+                        canvas.set_draw_color(Color::RGBA(255, 255, 255, volume_bar_alpha));
+                        canvas
+                            .fill_rect(Rect::new(
+                                volume_bar_rect.x(),
+                                volume_bar_rect.bottom() - filled_height as i32,
+                                volume_bar_rect.width(),
+                                filled_height,
+                            ))
+                            .unwrap();
+                    }
                 }
             }
         }
@@ -913,7 +2472,7 @@ fn main() {
         let info_qry = info_tex.query();
 
         if now_playing_resources.player_info.state() == PlayerState::Playing {
-            info_scroll_pos -= DT * 30.;
+            info_scroll_pos -= dt * 30.;
             info_scroll_pos %= (info_qry.width as i32 + INFO_SPACING) as f32;
         }
 
@@ -921,18 +2480,63 @@ fn main() {
         engine::copy_unscaled(
             &info_tex,
             info_scroll_pos as i32,
-            (ARTWORK_SIZE + INFO_PADDING) as i32,
+            (artwork_size + INFO_PADDING) as i32,
             &mut canvas,
         )
         .unwrap();
         engine::copy_unscaled(
             &info_tex,
             info_scroll_pos as i32 + info_qry.width as i32 + INFO_SPACING,
-            (ARTWORK_SIZE + INFO_PADDING) as i32,
+            (artwork_size + INFO_PADDING) as i32,
             &mut canvas,
         )
         .unwrap();
 
+        // Draw the restore prompt over the info text until it's answered, brightening the option that's hovered
+        if restore_prompt.is_some() {
+            canvas.set_blend_mode(BlendMode::None);
+            canvas.set_draw_color(Color::BLACK);
+            canvas.fill_rect(info_rect).unwrap();
+            for (i, (texture, rect)) in restore_prompt_textures.iter_mut().zip(restore_prompt_rects).enumerate() {
+                let color_mod = if i == 0 || rect.contains_point(mouse_state.pos()) {
+                    icon_color_mod_hover
+                } else {
+                    icon_color_mod_default
+                };
+                texture.set_color_mod(color_mod, color_mod, color_mod);
+                canvas.copy(texture, None, rect).unwrap();
+            }
+        }
+
+        // Draw the search query over the info text while searching, keeping the end of it visible if it's too long
+        if let Some(text) = search.as_ref().and_then(|search| search.text.as_ref()) {
+            canvas.set_blend_mode(BlendMode::None);
+            canvas.set_draw_color(Color::BLACK);
+            canvas.fill_rect(info_rect).unwrap();
+            let query = text.query();
+            let width = query.width.min(artwork_size - INFO_PADDING * 2);
+            canvas
+                .copy(
+                    text,
+                    Rect::new((query.width - width) as i32, 0, width, query.height),
+                    Rect::new(
+                        INFO_PADDING as i32,
+                        (artwork_size + INFO_PADDING) as i32,
+                        width,
+                        query.height,
+                    ),
+                )
+                .unwrap();
+        }
+
+        // Draw the toast over the info text, removing it once it has expired
+        if let Some(t) = toast.as_mut() {
+            t.render(&mut canvas, info_rect).unwrap();
+            if !t.update(dt) {
+                toast = None;
+            }
+        }
+
         // Draw each button and add its rect to the 'sub' vec if it's active, then deactivate every button
         sub.clear();
         for button in buttons.values() {
@@ -946,20 +2550,42 @@ fn main() {
                 ));
             }
         }
+        // Keep the seek bar and time readout from dragging the window
+        if current_view == View::Miniplayer && now_playing_resources.player_info.state() != PlayerState::Stopped {
+            sub.push(raw_heap_rect(seek_rect.x, seek_rect.y, seek_rect.w, seek_rect.h));
+        }
+        if let Some(rect) = time_readout_rect {
+            sub.push(raw_heap_rect(rect.x, rect.y, rect.w, rect.h));
+        }
+        if restore_prompt.is_some() {
+            for rect in &restore_prompt_rects[1..] {
+                sub.push(raw_heap_rect(rect.x, rect.y, rect.w, rect.h));
+            }
+        }
+        // Keep the lists in the queue, hidden albums, filter and album detail views from dragging the window so they can
+        // be scrolled and clicked
+        if matches!(current_view, View::Queue | View::Hidden | View::Filter | View::AlbumDetail) {
+            sub.push(raw_heap_rect(
+                queue_list_rect.x,
+                queue_list_rect.y,
+                queue_list_rect.w,
+                queue_list_rect.h,
+            ));
+        }
         if current_view == View::AlbumSelect {
             if dragged_item.is_some() {
                 sub.push(raw_heap_rect(
                     0,
                     0,
-                    WINDOW_WIDTH as i32,
-                    WINDOW_HEIGHT as i32,
+                    window_width as i32,
+                    window_height as i32,
                 ));
             } else {
                 sub.push(raw_heap_rect(
                     0,
                     0,
-                    ARTWORK_SIZE as i32,
-                    ARTWORK_SIZE as i32,
+                    artwork_size as i32,
+                    artwork_size as i32,
                 ));
             }
         }
@@ -970,16 +2596,47 @@ fn main() {
         canvas.set_blend_mode(BlendMode::Mod);
         canvas.set_draw_color(Color::RGB(200, 200, 200));
         canvas
-            .draw_rect(Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT))
+            .draw_rect(Rect::new(0, 0, window_width, window_height))
             .unwrap();
         canvas.set_blend_mode(BlendMode::Add);
         canvas.set_draw_color(Color::RGB(30, 30, 30));
         canvas
-            .draw_rect(Rect::new(1, 1, WINDOW_WIDTH - 2, WINDOW_HEIGHT - 2))
+            .draw_rect(Rect::new(1, 1, window_width - 2, window_height - 2))
             .unwrap();
 
+        // Save the frame if headless, quitting once enough frames have been rendered
+        frame_count += 1;
+        if let Some(options) = &headless {
+            if let Err(e) = options.save_frame(&canvas, frame_count - 1) {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+            if frame_count >= options.frames {
+                break 'running;
+            }
+        }
+
         //Present the canvas
         canvas.present();
-        thread::sleep(Duration::from_nanos(1_000_000_000u64 / FRAMES_PER_SECOND));
+        if headless.is_none() {
+            thread::sleep(Duration::from_nanos(1_000_000_000u64 / frames_per_second));
+        }
+    }
+
+    // Save the window's position and view so they can be restored on the next launch
+    if headless.is_none() {
+        // The album detail view isn't restored, since the album it showed won't be loaded yet
+        let view = if current_view == View::AlbumDetail { View::AlbumSelect } else { current_view };
+        if let Err(e) = WindowState::from_window(canvas.window(), view).and_then(|state| state.save()) {
+            println!("Unable to save window state: {e}");
+        }
+    }
+
+    // Save the albums left in the queue so they can be restored on the next launch. The saved queue is left as it is
+    // if the restore prompt wasn't answered, so it can be offered again.
+    if headless.is_none() && player_reachable && restore_prompt.is_none() {
+        if let Err(e) = SavedQueue::new(&backend_name, queued_albums).save() {
+            println!("Unable to save queue: {e}");
+        }
     }
 }
@@ -0,0 +1,669 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use image::{DynamicImage, ImageOutputFormat, Rgb, RgbImage};
+use serde::Deserialize;
+
+use crate::album_data::{AlbumData, AlbumTrack};
+use crate::backend::{PlayerCommand, PlayerBackend};
+use crate::error::{MicromusicError, Result};
+use crate::player_data::{PlayerData, PlayerInfo, PlayerState, RepeatMode, TrackInfo};
+use crate::queue_data::{self, QueuedTrack};
+
+/// An album in a mock library, as loaded from a library file passed with `--mock-library`
+#[derive(Deserialize, Clone)]
+pub struct MockAlbum {
+    album: String,
+    album_artist: String,
+    /// Path to an image file to use as the artwork, relative to the library file. If left out, artwork is generated
+    /// from the album's name.
+    #[serde(default)]
+    artwork: Option<String>,
+    #[serde(default)]
+    genre: Option<String>,
+    #[serde(default)]
+    year: Option<u32>,
+    #[serde(default)]
+    play_count: Option<u32>,
+    /// When the album was last played, in seconds since the Unix epoch
+    #[serde(default)]
+    last_played: Option<f64>,
+    tracks: Vec<MockTrack>,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct MockTrack {
+    name: String,
+    /// Defaults to the album artist
+    #[serde(default)]
+    artist: Option<String>,
+    /// Length in seconds
+    length: f64,
+}
+
+/// A track in the simulated queue
+struct MockQueueEntry {
+    /// The track as an (album index, track index) pair
+    track: (usize, usize),
+    /// Identifies this entry in the queue, since the same track can be queued more than once
+    id: u64,
+}
+
+/// The simulated state of the player
+struct MockPlayerState {
+    queue: Vec<MockQueueEntry>,
+    /// The ID to give the next entry added to the queue
+    next_id: u64,
+    current: usize,
+    /// The player position at `last_update`, which is a time from the clock
+    pos: f64,
+    playing: bool,
+    /// The volume from 0 to 100
+    volume: u8,
+    /// Shuffle and repeat are only displayed, and don't change the simulated playback order
+    shuffle: bool,
+    repeat: RepeatMode,
+    last_update: f64,
+    loved: HashSet<(usize, usize)>,
+}
+
+/// Gets the time in seconds that simulated playback follows. It's real time unless another clock is passed to
+/// `MockBackend::with_clock`, so that tests and headless renders don't depend on timing.
+pub type MockClock = Box<dyn Fn() -> f64 + Send + Sync>;
+
+/// A clock that follows real time from when it was created
+fn system_clock() -> MockClock {
+    let start = Instant::now();
+    Box::new(move || start.elapsed().as_secs_f64())
+}
+
+/// A backend that serves a scripted library and simulates playback in-process, for demos and testing without a music
+/// player. Playback starts on the first album, and queued albums are appended to a simulated DJ queue.
+pub struct MockBackend {
+    library: Vec<MockAlbum>,
+    state: Mutex<MockPlayerState>,
+    clock: MockClock,
+    /// The encoded artwork of each album by index, so it's only encoded once
+    artwork_cache: Mutex<HashMap<usize, Option<Vec<u8>>>>,
+}
+
+impl MockPlayerState {
+    fn current_track(&self) -> Option<(usize, usize)> {
+        self.queue.get(self.current).map(|entry| entry.track)
+    }
+
+    /// The index in the queue of the first track that hasn't started playing yet
+    fn upcoming_start(&self) -> usize {
+        (self.current + 1).min(self.queue.len())
+    }
+}
+
+impl MockBackend {
+    pub fn new(library: Vec<MockAlbum>) -> Self {
+        let queue: Vec<MockQueueEntry> = library
+            .first()
+            .map(|album| {
+                (0..album.tracks.len())
+                    .map(|i| MockQueueEntry { track: (0, i), id: i as u64 })
+                    .collect()
+            })
+            .unwrap_or_default();
+        MockBackend {
+            library,
+            state: Mutex::new(MockPlayerState {
+                next_id: queue.len() as u64,
+                queue,
+                current: 0,
+                pos: 0.,
+                playing: true,
+                volume: 50,
+                shuffle: false,
+                repeat: RepeatMode::Off,
+                last_update: 0.,
+                loved: HashSet::new(),
+            }),
+            clock: system_clock(),
+            artwork_cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replaces the clock that simulated playback follows, measuring playback from the clock's current time
+    pub fn with_clock(mut self, clock: MockClock) -> Self {
+        self.state.get_mut().unwrap().last_update = clock();
+        self.clock = clock;
+        self
+    }
+
+    /// Loads a library from a JSON file containing a list of albums
+    pub fn from_file(path: &Path) -> std::result::Result<Self, String> {
+        let file = std::fs::read_to_string(path).map_err(|e| format!("Unable to read {}: {e}", path.display()))?;
+        let mut library: Vec<MockAlbum> =
+            serde_json::from_str(&file).map_err(|e| format!("Invalid mock library {}: {e}", path.display()))?;
+        // Artwork paths are relative to the library file rather than the current directory
+        let dir = path.parent().unwrap_or(Path::new(""));
+        for album in &mut library {
+            if let Some(artwork) = &mut album.artwork {
+                *artwork = dir.join(&*artwork).to_string_lossy().into_owned();
+            }
+        }
+        Ok(Self::new(library))
+    }
+
+    /// A small built-in library with generated artwork
+    pub fn demo() -> Self {
+        const DEMO_ALBUMS: [(&str, &str, &str, u32); 12] = [
+            ("Tidal Lock", "The Orbiters", "Electronic", 2019),
+            ("Paper Lanterns", "Mira Vale", "Folk", 2011),
+            ("Low Static", "Cathode Choir", "Electronic", 1998),
+            ("Evergreen Hours", "Juniper Row", "Folk", 2004),
+            ("Salt & Signal", "Harbor Lights", "Rock", 1994),
+            ("Night Bus", "Metro Youth", "Hip-Hop", 2016),
+            ("Glass Orchard", "Mira Vale", "Folk", 2015),
+            ("Copper Sun", "Dune Sea", "Rock", 1979),
+            ("Quiet Machines", "Cathode Choir", "Electronic", 2002),
+            ("Sundial", "The Orbiters", "Electronic", 2022),
+            ("Wildflower Radio", "Juniper Row", "Folk", 2008),
+            ("Lighthouse Keeper", "Harbor Lights", "Rock", 1996),
+        ];
+        let library = DEMO_ALBUMS
+            .iter()
+            .enumerate()
+            .map(|(i, (album, album_artist, genre, year))| MockAlbum {
+                album: album.to_string(),
+                album_artist: album_artist.to_string(),
+                artwork: None,
+                genre: Some(genre.to_string()),
+                year: Some(*year),
+                play_count: Some((i * 7 % 12) as u32),
+                last_played: None,
+                tracks: (0..3 + i % 3)
+                    .map(|t| MockTrack {
+                        name: format!("Track {}", t + 1),
+                        artist: None,
+                        length: 150. + ((i * 7 + t * 13) % 9) as f64 * 10.,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Self::new(library)
+    }
+
+    /// Gets the artwork of the album at an index, loading or generating it the first time it's needed
+    fn cached_artwork(&self, album_i: usize) -> Option<Vec<u8>> {
+        let mut artwork_cache = self.artwork_cache.lock().unwrap();
+        artwork_cache
+            .entry(album_i)
+            .or_insert_with(|| Self::artwork(&self.library[album_i]))
+            .clone()
+    }
+
+    /// Loads an album's artwork from its file, or generates a two-color gradient based on its name if it doesn't
+    /// have one or it can't be read
+    fn artwork(album: &MockAlbum) -> Option<Vec<u8>> {
+        if let Some(path) = &album.artwork {
+            match std::fs::read(path) {
+                Ok(artwork) => return Some(artwork),
+                Err(e) => println!("Unable to read artwork {path} for {}: {e}", album.album),
+            }
+        }
+
+        let hash = format!("{}{}", album.album_artist, album.album)
+            .bytes()
+            .fold(5381u32, |hash, b| hash.wrapping_mul(33) ^ b as u32);
+        let [r, g, b, _] = hash.to_le_bytes();
+        let from = [r, g, b];
+        let to = [255 - g, 255 - b, 255 - r];
+
+        const SIZE: u32 = 300;
+        let image = RgbImage::from_fn(SIZE, SIZE, |x, y| {
+            let t = (x + y) as f32 / (SIZE * 2) as f32;
+            Rgb([0, 1, 2].map(|c| (from[c] as f32 * (1. - t) + to[c] as f32 * t) as u8))
+        });
+
+        let mut bytes = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut bytes, ImageOutputFormat::Png)
+            .ok()?;
+        Some(bytes.into_inner())
+    }
+
+    fn track(&self, (album, track): (usize, usize)) -> &MockTrack {
+        &self.library[album].tracks[track]
+    }
+
+    /// Advances simulated playback to the clock's current time, moving on to the next track whenever one ends
+    fn advance(&self, state: &mut MockPlayerState) {
+        let now = (self.clock)();
+        if state.playing {
+            state.pos += (now - state.last_update).max(0.);
+            while let Some(current) = state.current_track() {
+                let length = self.track(current).length;
+                if state.pos < length {
+                    break;
+                }
+                state.pos -= length;
+                state.current += 1;
+            }
+            if state.current >= state.queue.len() {
+                state.playing = false;
+                state.pos = 0.;
+            }
+        }
+        state.last_update = now;
+    }
+
+    /// Finds the index of an album in the library
+    fn album_index(&self, album: &str, album_artist: &str) -> Result<usize> {
+        self.library
+            .iter()
+            .position(|a| a.album == album && a.album_artist == album_artist)
+            .ok_or_else(|| MicromusicError::Script(format!("{album} by {album_artist} isn't in the mock library")))
+    }
+
+    /// Adds an album's tracks to the queue, either right after the current track or at the end, and starts playing it
+    /// if nothing is playing and `start_playback` is set
+    fn add_album(&self, album: &str, album_artist: &str, play_next: bool, start_playback: bool) -> Result<()> {
+        let album_i = self.album_index(album, album_artist)?;
+        let tracks = (0..self.library[album_i].tracks.len()).map(|i| (album_i, i));
+        self.add_tracks(tracks, play_next, start_playback);
+        Ok(())
+    }
+
+    /// Adds tracks to the queue, either right after the current track or at the end, and starts playing them if nothing
+    /// is playing and `start_playback` is set
+    fn add_tracks(&self, tracks: impl Iterator<Item = (usize, usize)>, play_next: bool, start_playback: bool) {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+
+        if start_playback && !state.playing && state.current >= state.queue.len() {
+            state.current = state.queue.len();
+            state.pos = 0.;
+            state.playing = true;
+        }
+        let insert_at = if play_next { state.upcoming_start() } else { state.queue.len() };
+        let entries: Vec<MockQueueEntry> = tracks
+            .map(|track| {
+                let id = state.next_id;
+                state.next_id += 1;
+                MockQueueEntry { track, id }
+            })
+            .collect();
+        state.queue.splice(insert_at..insert_at, entries);
+    }
+}
+
+impl PlayerBackend for MockBackend {
+    fn get_player_data(&self) -> Result<Option<PlayerData>> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+
+        let Some(current) = state.current_track() else {
+            return Ok(None);
+        };
+        let album = &self.library[current.0];
+        let track = self.track(current);
+
+        Ok(Some(PlayerData {
+            track_info: TrackInfo::new(
+                track.name.clone(),
+                track.artist.clone().unwrap_or_else(|| album.album_artist.clone()),
+                album.album.clone(),
+                album.album_artist.clone(),
+                state.loved.contains(&current),
+                track.length,
+            ),
+            player_info: PlayerInfo::new(
+                state.pos,
+                true,
+                if state.playing { PlayerState::Playing } else { PlayerState::Paused },
+                Some(state.volume),
+                state.shuffle,
+                state.repeat,
+            ),
+            track_artwork_data: self.cached_artwork(current.0),
+        }))
+    }
+
+    fn run_command(&self, command: PlayerCommand) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+
+        match command {
+            PlayerCommand::PlayPause => {
+                if state.current >= state.queue.len() {
+                    state.current = 0;
+                }
+                state.playing = !state.playing && !state.queue.is_empty();
+            }
+            PlayerCommand::NextTrack => {
+                state.current += 1;
+                state.pos = 0.;
+                if state.current >= state.queue.len() {
+                    state.playing = false;
+                }
+            }
+            PlayerCommand::BackTrack => {
+                if state.pos < 3. {
+                    state.current = state.current.saturating_sub(1);
+                }
+                state.pos = 0.;
+            }
+            PlayerCommand::Seek(pos) => {
+                if let Some(current) = state.current_track() {
+                    state.pos = pos.clamp(0., self.track(current).length);
+                }
+            }
+            PlayerCommand::SetVolume(volume) => state.volume = volume.min(100),
+            PlayerCommand::SetShuffle(shuffle) => state.shuffle = shuffle,
+            PlayerCommand::SetRepeat(repeat) => state.repeat = repeat,
+            PlayerCommand::Love | PlayerCommand::Unlove => {
+                if let Some(current) = state.current_track() {
+                    if matches!(command, PlayerCommand::Love) {
+                        state.loved.insert(current);
+                    } else {
+                        state.loved.remove(&current);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_album_data(&self, cached_albums: Vec<String>) -> Result<Vec<AlbumData>> {
+        Ok(self.library
+            .iter()
+            .enumerate()
+            .filter_map(|(i, album)| {
+                let mut artwork_data = None;
+                if !cached_albums.contains(&format!("{}{}", album.album_artist, album.album)) {
+                    artwork_data = Some(self.cached_artwork(i)?);
+                }
+                Some(AlbumData {
+                    album: album.album.clone(),
+                    album_artist: album.album_artist.clone(),
+                    artwork_data,
+                    genre: album.genre.clone(),
+                    year: album.year,
+                    play_count: album.play_count,
+                    last_played: album.last_played,
+                })
+            })
+            .collect())
+    }
+
+    fn queue_album(&self, album: &str, album_artist: &str, start_playback: bool) -> Result<()> {
+        self.add_album(album, album_artist, false, start_playback)
+    }
+
+    fn play_album_next(&self, album: &str, album_artist: &str) -> Result<()> {
+        self.add_album(album, album_artist, true, true)
+    }
+
+    fn get_album_tracks(&self, album: &str, album_artist: &str) -> Result<Vec<AlbumTrack>> {
+        let album_i = self.album_index(album, album_artist)?;
+        let album = &self.library[album_i];
+        Ok(album
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(i, track)| AlbumTrack {
+                // Tracks are identified by their (album index, track index) pair
+                id: format!("{album_i}:{i}"),
+                name: track.name.clone(),
+                artist: track.artist.clone().unwrap_or_else(|| album.album_artist.clone()),
+                length: track.length,
+            })
+            .collect())
+    }
+
+    fn queue_tracks(&self, ids: &[String]) -> Result<()> {
+        let tracks = ids
+            .iter()
+            .map(|id| {
+                id.split_once(':')
+                    .and_then(|(album, track)| Some((album.parse().ok()?, track.parse().ok()?)))
+                    .filter(|&(album, track): &(usize, usize)| {
+                        self.library.get(album).is_some_and(|a| track < a.tracks.len())
+                    })
+                    .ok_or_else(|| MicromusicError::Script(format!("{id} isn't a track in the mock library")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        self.add_tracks(tracks.into_iter(), false, true);
+        Ok(())
+    }
+
+    fn make_dj_playlist(&self, _keep_tracks: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_played_tracks(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let played = state.current.min(state.queue.len());
+        state.queue.drain(..played);
+        state.current -= played;
+        Ok(())
+    }
+
+    fn get_queue(&self) -> Result<Vec<QueuedTrack>> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+        Ok(state.queue[state.upcoming_start()..]
+            .iter()
+            .map(|entry| {
+                let album = &self.library[entry.track.0];
+                let track = self.track(entry.track);
+                QueuedTrack {
+                    id: entry.id.to_string(),
+                    name: track.name.clone(),
+                    artist: track.artist.clone().unwrap_or_else(|| album.album_artist.clone()),
+                    album: album.album.clone(),
+                    album_artist: album.album_artist.clone(),
+                }
+            })
+            .collect())
+    }
+
+    fn remove_from_queue(&self, ids: &[String]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+        let start = state.upcoming_start();
+        let upcoming = state.queue.split_off(start);
+        state
+            .queue
+            .extend(upcoming.into_iter().filter(|entry| !ids.contains(&entry.id.to_string())));
+        Ok(())
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+        let start = state.upcoming_start();
+        state.queue.truncate(start);
+        Ok(())
+    }
+
+    fn move_in_queue(&self, ids: &[String], before: Option<&str>) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        self.advance(&mut state);
+        let start = state.upcoming_start();
+        let upcoming = state.queue.split_off(start);
+        let upcoming_ids: Vec<String> = upcoming.iter().map(|entry| entry.id.to_string()).collect();
+
+        let mut upcoming: Vec<Option<MockQueueEntry>> = upcoming.into_iter().map(Some).collect();
+        for i in queue_data::move_block(&upcoming_ids, ids, before) {
+            state.queue.extend(upcoming[i].take());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    /// A clock that only moves when the test sets it
+    fn manual_clock() -> (MockClock, Arc<Mutex<f64>>) {
+        let time = Arc::new(Mutex::new(0.));
+        let clock_time = time.clone();
+        (Box::new(move || *clock_time.lock().unwrap()), time)
+    }
+
+    /// An album by "Artist" whose tracks are 10 seconds long
+    fn album(album: &str, tracks: &[&str]) -> MockAlbum {
+        MockAlbum {
+            album: album.to_string(),
+            album_artist: String::from("Artist"),
+            artwork: None,
+            genre: None,
+            year: None,
+            play_count: None,
+            last_played: None,
+            tracks: tracks
+                .iter()
+                .map(|name| MockTrack { name: name.to_string(), artist: None, length: 10. })
+                .collect(),
+        }
+    }
+
+    /// A backend with two albums, playing the first album from the start
+    fn backend() -> (MockBackend, Arc<Mutex<f64>>) {
+        let (clock, time) = manual_clock();
+        let backend = MockBackend::new(vec![album("First", &["A", "B"]), album("Second", &["C"])]).with_clock(clock);
+        (backend, time)
+    }
+
+    /// The name, position and state of the current track
+    fn now_playing(backend: &MockBackend) -> Option<(String, f64, PlayerState)> {
+        backend.get_player_data().unwrap().map(|data| {
+            (data.track_info.name().to_string(), data.player_info.pos(), data.player_info.state())
+        })
+    }
+
+    #[test]
+    fn playback_follows_clock() {
+        let (backend, time) = backend();
+        assert!(now_playing(&backend) == Some((String::from("A"), 0., PlayerState::Playing)));
+        *time.lock().unwrap() = 4.;
+        assert!(now_playing(&backend) == Some((String::from("A"), 4., PlayerState::Playing)));
+        // Moves on to the next track when one ends
+        *time.lock().unwrap() = 12.;
+        assert!(now_playing(&backend) == Some((String::from("B"), 2., PlayerState::Playing)));
+        // Stops at the end of the queue
+        *time.lock().unwrap() = 30.;
+        assert!(now_playing(&backend).is_none());
+    }
+
+    #[test]
+    fn play_pause() {
+        let (backend, time) = backend();
+        *time.lock().unwrap() = 3.;
+        backend.run_command(PlayerCommand::PlayPause).unwrap();
+        assert!(now_playing(&backend) == Some((String::from("A"), 3., PlayerState::Paused)));
+        // The position doesn't move while paused
+        *time.lock().unwrap() = 8.;
+        assert!(now_playing(&backend) == Some((String::from("A"), 3., PlayerState::Paused)));
+        backend.run_command(PlayerCommand::PlayPause).unwrap();
+        *time.lock().unwrap() = 9.;
+        assert!(now_playing(&backend) == Some((String::from("A"), 4., PlayerState::Playing)));
+    }
+
+    #[test]
+    fn next_track() {
+        let (backend, time) = backend();
+        *time.lock().unwrap() = 5.;
+        backend.run_command(PlayerCommand::NextTrack).unwrap();
+        assert!(now_playing(&backend) == Some((String::from("B"), 0., PlayerState::Playing)));
+        // Skipping past the last track stops playback
+        backend.run_command(PlayerCommand::NextTrack).unwrap();
+        assert!(now_playing(&backend).is_none());
+    }
+
+    #[test]
+    fn back_track() {
+        let (backend, time) = backend();
+        backend.run_command(PlayerCommand::NextTrack).unwrap();
+        // Goes back to the start of the track unless it has only just started
+        *time.lock().unwrap() = 5.;
+        backend.run_command(PlayerCommand::BackTrack).unwrap();
+        assert!(now_playing(&backend) == Some((String::from("B"), 0., PlayerState::Playing)));
+        *time.lock().unwrap() = 6.;
+        backend.run_command(PlayerCommand::BackTrack).unwrap();
+        assert!(now_playing(&backend) == Some((String::from("A"), 0., PlayerState::Playing)));
+        // There's nothing before the first track
+        backend.run_command(PlayerCommand::BackTrack).unwrap();
+        assert!(now_playing(&backend) == Some((String::from("A"), 0., PlayerState::Playing)));
+    }
+
+    #[test]
+    fn love() {
+        let (backend, _) = backend();
+        let loved = |backend: &MockBackend| backend.get_player_data().unwrap().unwrap().track_info.loved();
+        assert!(!loved(&backend));
+        backend.run_command(PlayerCommand::Love).unwrap();
+        assert!(loved(&backend));
+        // Only the track that was loved is loved
+        backend.run_command(PlayerCommand::NextTrack).unwrap();
+        assert!(!loved(&backend));
+        backend.run_command(PlayerCommand::BackTrack).unwrap();
+        assert!(loved(&backend));
+        backend.run_command(PlayerCommand::Unlove).unwrap();
+        assert!(!loved(&backend));
+    }
+
+    #[test]
+    fn queued_album_plays_after_queue() {
+        let (backend, time) = backend();
+        backend.queue_album("Second", "Artist", true).unwrap();
+        *time.lock().unwrap() = 25.;
+        assert!(now_playing(&backend) == Some((String::from("C"), 5., PlayerState::Playing)));
+    }
+
+    #[test]
+    fn restored_album_waits_for_playback() {
+        let (backend, time) = backend();
+        *time.lock().unwrap() = 30.;
+        assert!(now_playing(&backend).is_none());
+        backend.queue_album("Second", "Artist", false).unwrap();
+        *time.lock().unwrap() = 40.;
+        assert!(now_playing(&backend) == Some((String::from("C"), 0., PlayerState::Paused)));
+    }
+
+    #[test]
+    fn unreadable_artwork_falls_back_to_gradient() {
+        let mut missing = album("Missing", &["A"]);
+        missing.artwork = Some(String::from("/nonexistent/micromusic/cover.png"));
+        let backend = MockBackend::new(vec![missing.clone()]);
+        let albums = backend.get_album_data(Vec::new()).unwrap();
+        assert_eq!(albums.len(), 1);
+        missing.artwork = None;
+        assert!(albums[0].artwork_data == MockBackend::artwork(&missing));
+    }
+
+    #[test]
+    fn artwork_paths_are_relative_to_library() {
+        let dir = std::env::temp_dir().join(format!("micromusic-mock-library-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("covers")).unwrap();
+        std::fs::write(dir.join("covers/first.png"), b"cover").unwrap();
+        let library = r#"[{"album": "First", "album_artist": "Artist", "artwork": "covers/first.png", "tracks": []}]"#;
+        std::fs::write(dir.join("library.json"), library).unwrap();
+
+        let backend = MockBackend::from_file(&dir.join("library.json")).unwrap();
+        let albums = backend.get_album_data(Vec::new()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(albums[0].artwork_data.as_deref(), Some(&b"cover"[..]));
+    }
+
+    #[test]
+    fn artwork_is_encoded_once() {
+        let (backend, _) = backend();
+        let artwork = backend.get_player_data().unwrap().unwrap().track_artwork_data;
+        assert!(artwork.is_some());
+        assert_eq!(backend.artwork_cache.lock().unwrap().len(), 1);
+        assert!(backend.get_player_data().unwrap().unwrap().track_artwork_data == artwork);
+        assert_eq!(backend.artwork_cache.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,849 @@
+use std::collections::HashSet;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::Mutex;
+
+use crate::album_data::{AlbumData, AlbumTrack};
+use crate::backend::{PlayerCommand, PlayerBackend};
+use crate::error::{MicromusicError, Result};
+use crate::player_data::{PlayerData, PlayerInfo, PlayerState, RepeatMode, TrackInfo};
+use crate::queue_data::{self, QueuedTrack};
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: &str = "6600";
+
+/// A socket connected to MPD, over either TCP or a Unix domain socket
+trait Stream: Read + Write + Send {}
+impl<T: Read + Write + Send> Stream for T {}
+
+/// The parsed response to an MPD command: a list of key-value pairs, plus a binary blob for commands that send one
+#[derive(Default)]
+struct MpdResponse {
+    pairs: Vec<(String, String)>,
+    binary: Vec<u8>,
+}
+
+impl MpdResponse {
+    /// Gets the first value for a key
+    fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A backend that controls an MPD server using its text protocol. The server is located with the `MPD_HOST` and
+/// `MPD_PORT` environment variables like other MPD clients, and MPD's own play queue is used as the micromusic DJ
+/// queue.
+pub struct MpdBackend {
+    host: String,
+    port: String,
+    password: Option<String>,
+    connection: Mutex<Option<BufReader<Box<dyn Stream>>>>,
+    /// The URI and artwork of the last track that was sent to the main thread, so it isn't fetched on every update
+    artwork_cache: Mutex<(String, Option<Vec<u8>>)>,
+    /// The IDs of the songs that micromusic has queued this session. The queue is shared with other clients, so these
+    /// are the only songs that are cleared once they've been played.
+    queued_ids: Mutex<HashSet<String>>,
+}
+
+impl MpdBackend {
+    pub fn new() -> Result<Self> {
+        // MPD_HOST may be prefixed with a password, e.g. "password@host"
+        let host = std::env::var("MPD_HOST").unwrap_or_else(|_| DEFAULT_HOST.to_string());
+        let (password, host) = match host.rsplit_once('@') {
+            Some((password, host)) if !password.is_empty() => (Some(password.to_string()), host.to_string()),
+            _ => (None, host),
+        };
+        let backend = MpdBackend {
+            host,
+            port: std::env::var("MPD_PORT").unwrap_or_else(|_| DEFAULT_PORT.to_string()),
+            password,
+            connection: Mutex::new(None),
+            artwork_cache: Mutex::new((String::new(), None)),
+            queued_ids: Mutex::new(HashSet::new()),
+        };
+        // Connect immediately so that a bad address is reported at startup
+        backend.request("ping")?;
+        Ok(backend)
+    }
+
+    fn connect(&self) -> Result<BufReader<Box<dyn Stream>>> {
+        let stream: Box<dyn Stream> = if self.host.starts_with('/') {
+            #[cfg(unix)]
+            {
+                Box::new(UnixStream::connect(&self.host)?)
+            }
+            #[cfg(not(unix))]
+            {
+                return Err(io::Error::new(io::ErrorKind::Unsupported, "Unix sockets are not supported").into());
+            }
+        } else {
+            Box::new(TcpStream::connect(format!("{}:{}", self.host, self.port))?)
+        };
+        let mut reader = BufReader::new(stream);
+
+        let mut greeting = String::new();
+        reader.read_line(&mut greeting)?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Server is not MPD").into());
+        }
+        if let Some(password) = &self.password {
+            send_command(&mut reader, &format!("password {}", quote(password)))?;
+        }
+        Ok(reader)
+    }
+
+    /// Sends a command to MPD, connecting first if necessary, and waits for its response. The connection is dropped
+    /// on any IO error so that the next request reconnects.
+    fn request(&self, command: &str) -> Result<MpdResponse> {
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(self.connect()?);
+        }
+        let result = send_command(connection.as_mut().unwrap(), command);
+        if let Err(MicromusicError::Connection(_)) = &result {
+            *connection = None;
+        }
+        result
+    }
+
+    /// Reads a picture in chunks using a command that takes a song URI and offset (`albumart` or `readpicture`)
+    fn read_binary(&self, command: &str, uri: &str) -> Option<Vec<u8>> {
+        let mut data = Vec::new();
+        loop {
+            let response = self
+                .request(&format!("{command} {} {}", quote(uri), data.len()))
+                .ok()?;
+            let size: usize = response.get("size")?.parse().ok()?;
+            if response.binary.is_empty() {
+                return None;
+            }
+            data.extend_from_slice(&response.binary);
+            if data.len() >= size {
+                return Some(data);
+            }
+        }
+    }
+
+    /// Gets the artwork for a song, preferring a cover file in its directory over an embedded picture
+    fn artwork(&self, uri: &str) -> Option<Vec<u8>> {
+        self.read_binary("albumart", uri)
+            .or_else(|| self.read_binary("readpicture", uri))
+    }
+
+    /// Remembers the songs from `start` to the end of the queue as queued by micromusic
+    fn remember_queued(&self, start: usize) -> Result<()> {
+        let length = playlist_length(&self.request("status")?);
+        if start < length {
+            let songs = split_songs(self.request(&format!("playlistinfo {start}:{length}"))?);
+            let mut queued_ids = self.queued_ids.lock().unwrap();
+            queued_ids.extend(songs.iter().filter_map(|song| song.get("Id").map(String::from)));
+        }
+        Ok(())
+    }
+
+    /// Gets the position of the first upcoming song and the IDs of every upcoming song, in order
+    fn upcoming_ids(&self) -> Result<(usize, Vec<String>)> {
+        let start = upcoming_start(&self.request("status")?);
+        let ids = split_songs(self.request("playlistinfo")?)
+            .into_iter()
+            .filter(|song| song.get("Pos").and_then(|p| p.parse::<usize>().ok()) >= Some(start))
+            .filter_map(|song| song.get("Id").map(String::from))
+            .collect();
+        Ok((start, ids))
+    }
+}
+
+/// Gets the position in MPD's queue of the first song that hasn't started playing yet. When MPD is stopped, the
+/// current song is the one that will play next.
+fn upcoming_start(status: &MpdResponse) -> usize {
+    let song = status.get("song").and_then(|s| s.parse::<usize>().ok());
+    match (status.get("state"), song) {
+        (Some("stop"), Some(song)) => song,
+        (_, Some(song)) => song + 1,
+        (_, None) => 0,
+    }
+}
+
+/// Gets the number of songs in MPD's queue
+fn playlist_length(status: &MpdResponse) -> usize {
+    status.get("playlistlength").and_then(|l| l.parse().ok()).unwrap_or(0)
+}
+
+/// Splits the response to a command that lists songs (like `playlistinfo`) into one response per song
+fn split_songs(response: MpdResponse) -> Vec<MpdResponse> {
+    let mut songs: Vec<MpdResponse> = Vec::new();
+    for (key, value) in response.pairs {
+        if key == "file" || songs.is_empty() {
+            songs.push(MpdResponse::default());
+        }
+        songs.last_mut().unwrap().pairs.push((key, value));
+    }
+    songs
+}
+
+/// Quotes an argument to an MPD command
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Writes a command to the stream and parses its response. An `ACK` from MPD is returned as a script error.
+fn send_command(reader: &mut BufReader<Box<dyn Stream>>, command: &str) -> Result<MpdResponse> {
+    reader.get_mut().write_all(format!("{command}\n").as_bytes())?;
+
+    let mut response = MpdResponse::default();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "MPD closed the connection").into());
+        }
+        let line = line.trim_end_matches('\n');
+
+        if line == "OK" {
+            return Ok(response);
+        } else if let Some(message) = line.strip_prefix("ACK ") {
+            return Err(MicromusicError::Script(message.to_string()));
+        } else if let Some((key, value)) = line.split_once(": ") {
+            if key == "binary" {
+                let length: usize = value
+                    .parse()
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid binary length"))?;
+                let mut binary = vec![0; length + 1]; // followed by a newline
+                reader.read_exact(&mut binary)?;
+                binary.truncate(length);
+                response.binary = binary;
+            } else {
+                response.pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+impl PlayerBackend for MpdBackend {
+    fn get_player_data(&self) -> Result<Option<PlayerData>> {
+        let status = self.request("status")?;
+        let state = match status.get("state") {
+            Some("play") => PlayerState::Playing,
+            Some("pause") => PlayerState::Paused,
+            _ => return Ok(None),
+        };
+        let song = self.request("currentsong")?;
+        let Some(uri) = song.get("file").map(String::from) else {
+            return Ok(None);
+        };
+
+        let length = status
+            .get("duration")
+            .or(song.get("duration"))
+            .or(song.get("Time"))
+            .and_then(|d| d.parse::<f64>().ok())
+            .filter(|l| *l > 0.)
+            .unwrap_or(1.0);
+        let pos = status
+            .get("elapsed")
+            .and_then(|e| e.parse::<f64>().ok())
+            .unwrap_or(0.);
+        // The volume is -1 (or missing on older versions) if MPD has no mixer
+        let volume = status.get("volume").and_then(|v| v.parse::<u8>().ok());
+        // Repeating a single track is done with both repeat and single mode enabled
+        let repeat = match (status.get("repeat"), status.get("single")) {
+            (Some("1"), Some("1")) => RepeatMode::One,
+            (Some("1"), _) => RepeatMode::All,
+            _ => RepeatMode::Off,
+        };
+
+        // "Loved" songs are stored in MPD's sticker database, if it's enabled
+        let loved = self
+            .request(&format!("sticker get song {} loved", quote(&uri)))
+            .map(|response| response.get("sticker") == Some("loved=1"))
+            .unwrap_or(false);
+
+        let mut artwork_cache = self.artwork_cache.lock().unwrap();
+        if artwork_cache.0 != uri {
+            *artwork_cache = (uri.clone(), self.artwork(&uri));
+        }
+
+        Ok(Some(PlayerData {
+            track_info: TrackInfo::new(
+                song.get("Title").unwrap_or(&uri).to_string(),
+                song.get("Artist").unwrap_or_default().to_string(),
+                song.get("Album").unwrap_or_default().to_string(),
+                song.get("AlbumArtist").or(song.get("Artist")).unwrap_or_default().to_string(),
+                loved,
+                length,
+            ),
+            // MPD's play queue is always used as the DJ queue
+            player_info: PlayerInfo::new(pos, true, state, volume, status.get("random") == Some("1"), repeat),
+            track_artwork_data: artwork_cache.1.clone(),
+        }))
+    }
+
+    fn run_command(&self, command: PlayerCommand) -> Result<()> {
+        let command = match command {
+            PlayerCommand::PlayPause => match self.request("status")?.get("state") {
+                Some("stop") => String::from("play"),
+                _ => String::from("pause"),
+            },
+            PlayerCommand::NextTrack => String::from("next"),
+            PlayerCommand::BackTrack => {
+                // Like Apple Music, go back to the start of the track unless it has only just started
+                let elapsed = self
+                    .request("status")?
+                    .get("elapsed")
+                    .and_then(|e| e.parse::<f64>().ok())
+                    .unwrap_or(0.);
+                if elapsed > 3. {
+                    String::from("seekcur 0")
+                } else {
+                    String::from("previous")
+                }
+            }
+            PlayerCommand::Seek(pos) => format!("seekcur {pos:.3}"),
+            PlayerCommand::SetVolume(volume) => format!("setvol {}", volume.min(100)),
+            PlayerCommand::SetShuffle(shuffle) => format!("random {}", shuffle as u8),
+            PlayerCommand::SetRepeat(repeat) => {
+                let (repeat, single) = match repeat {
+                    RepeatMode::Off => (0, 0),
+                    RepeatMode::One => (1, 1),
+                    RepeatMode::All => (1, 0),
+                };
+                format!("command_list_begin\nrepeat {repeat}\nsingle {single}\ncommand_list_end")
+            }
+            PlayerCommand::Love | PlayerCommand::Unlove => {
+                let Some(uri) = self.request("currentsong")?.get("file").map(String::from) else {
+                    return Ok(());
+                };
+                if matches!(command, PlayerCommand::Love) {
+                    format!("sticker set song {} loved 1", quote(&uri))
+                } else {
+                    format!("sticker delete song {} loved", quote(&uri))
+                }
+            }
+        };
+        self.request(&command)?;
+        Ok(())
+    }
+
+    fn get_album_data(&self, cached_albums: Vec<String>) -> Result<Vec<AlbumData>> {
+        let response = self.request("list album group albumartist group genre group date")?;
+
+        // Each group of albums is preceded by the album artist, genre and date they belong to. An album with tracks from
+        // more than one genre or date is listed once for each, and the duplicates are removed later.
+        let mut albums = Vec::new();
+        let mut album_artist = String::new();
+        let mut genre = None;
+        let mut year = None;
+        for (key, value) in response.pairs {
+            if key.eq_ignore_ascii_case("AlbumArtist") {
+                album_artist = value;
+            } else if key.eq_ignore_ascii_case("Genre") {
+                genre = Some(value).filter(|genre| !genre.is_empty());
+            } else if key.eq_ignore_ascii_case("Date") {
+                // Dates may be full dates like 1994-03-21, so only the year is used
+                year = value.get(..4).and_then(|year| year.parse().ok());
+            } else if key.eq_ignore_ascii_case("Album") && !value.is_empty() {
+                albums.push((value, album_artist.clone(), genre.clone(), year));
+            }
+        }
+
+        let mut album_data = Vec::new();
+        for (album, album_artist, genre, year) in albums {
+            let mut artwork_data = None;
+            if !cached_albums.contains(&format!("{album_artist}{album}")) {
+                // Get the artwork from the first song on the album, ignoring albums without artwork
+                let song = self.request(&format!(
+                    "find albumartist {} album {} window 0:1",
+                    quote(&album_artist),
+                    quote(&album)
+                ))?;
+                artwork_data = song.get("file").and_then(|uri| self.artwork(uri));
+                if artwork_data.is_none() {
+                    continue;
+                }
+            }
+            album_data.push(AlbumData {
+                album,
+                album_artist,
+                artwork_data,
+                genre,
+                year,
+                // MPD doesn't keep track of plays
+                play_count: None,
+                last_played: None,
+            });
+        }
+        Ok(album_data)
+    }
+
+    fn queue_album(&self, album: &str, album_artist: &str, start_playback: bool) -> Result<()> {
+        let status = self.request("status")?;
+        let queue_length = playlist_length(&status);
+
+        self.request(&format!(
+            "findadd albumartist {} album {}",
+            quote(album_artist),
+            quote(album)
+        ))?;
+        self.remember_queued(queue_length)?;
+
+        // Start playing from the queued album if nothing else is playing, leaving random mode as the user set it
+        if start_playback && status.get("state") == Some("stop") {
+            self.request(&format!("play {queue_length}"))?;
+        }
+        Ok(())
+    }
+
+    fn play_album_next(&self, album: &str, album_artist: &str) -> Result<()> {
+        let status = self.request("status")?;
+        let start = upcoming_start(&status);
+        let queue_length = playlist_length(&status);
+
+        self.request(&format!(
+            "findadd albumartist {} album {}",
+            quote(album_artist),
+            quote(album)
+        ))?;
+        self.remember_queued(queue_length)?;
+
+        // Move the album from the end of the queue to right after the current song
+        let new_length = playlist_length(&self.request("status")?);
+        if start < queue_length && queue_length < new_length {
+            self.request(&format!("move {queue_length}:{new_length} {start}"))?;
+        }
+
+        // Start playing from the queued album if nothing else is playing
+        if status.get("state") == Some("stop") {
+            self.request(&format!("play {start}"))?;
+        }
+        Ok(())
+    }
+
+    fn get_album_tracks(&self, album: &str, album_artist: &str) -> Result<Vec<AlbumTrack>> {
+        let response = self.request(&format!("find albumartist {} album {}", quote(album_artist), quote(album)))?;
+
+        // Disc and track numbers may be written like "3/12", and only the first number is needed for sorting
+        let number = |song: &MpdResponse, key: &str| -> u32 {
+            song.get(key)
+                .and_then(|n| n.split('/').next())
+                .and_then(|n| n.trim().parse().ok())
+                .unwrap_or(0)
+        };
+        let mut songs = split_songs(response);
+        songs.sort_by_key(|song| (number(song, "Disc"), number(song, "Track")));
+
+        Ok(songs
+            .into_iter()
+            .filter_map(|song| {
+                let uri = song.get("file")?.to_string();
+                Some(AlbumTrack {
+                    name: song.get("Title").unwrap_or(&uri).to_string(),
+                    artist: song.get("Artist").unwrap_or(album_artist).to_string(),
+                    length: song
+                        .get("duration")
+                        .or(song.get("Time"))
+                        .and_then(|d| d.parse().ok())
+                        .unwrap_or(0.),
+                    // Songs are identified by their URI
+                    id: uri,
+                })
+            })
+            .collect())
+    }
+
+    fn queue_tracks(&self, ids: &[String]) -> Result<()> {
+        let status = self.request("status")?;
+        let queue_length = playlist_length(&status);
+
+        let adds: Vec<String> = ids.iter().map(|uri| format!("add {}", quote(uri))).collect();
+        self.request(&format!("command_list_begin\n{}\ncommand_list_end", adds.join("\n")))?;
+        self.remember_queued(queue_length)?;
+
+        // Start playing from the queued tracks if nothing else is playing
+        if status.get("state") == Some("stop") {
+            self.request(&format!("play {queue_length}"))?;
+        }
+        Ok(())
+    }
+
+    fn make_dj_playlist(&self, _keep_tracks: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_played_tracks(&self) -> Result<()> {
+        let status = self.request("status")?;
+        // With random playback, the songs before the current one haven't necessarily been played, and with repeat on
+        // they'll be played again
+        if status.get("random") == Some("1") || status.get("repeat") == Some("1") {
+            return Ok(());
+        }
+        let Some(song) = status.get("song").and_then(|s| s.parse::<usize>().ok()).filter(|song| *song > 0) else {
+            return Ok(());
+        };
+
+        // Only the songs that micromusic queued are removed, leaving the rest of the user's queue alone
+        let played = split_songs(self.request(&format!("playlistinfo 0:{song}"))?);
+        let commands: Vec<String> = {
+            let mut queued_ids = self.queued_ids.lock().unwrap();
+            played
+                .iter()
+                .filter_map(|song| song.get("Id"))
+                .filter(|id| queued_ids.remove(*id))
+                .map(|id| format!("deleteid {id}"))
+                .collect()
+        };
+        if !commands.is_empty() {
+            self.request(&format!("command_list_begin\n{}\ncommand_list_end", commands.join("\n")))?;
+        }
+        Ok(())
+    }
+
+    fn get_queue(&self) -> Result<Vec<QueuedTrack>> {
+        let start = upcoming_start(&self.request("status")?);
+        let songs = split_songs(self.request("playlistinfo")?);
+        Ok(songs
+            .into_iter()
+            .filter(|song| song.get("Pos").and_then(|p| p.parse::<usize>().ok()) >= Some(start))
+            .filter_map(|song| {
+                let artist = song.get("Artist").unwrap_or_default();
+                Some(QueuedTrack {
+                    id: song.get("Id")?.to_string(),
+                    name: song.get("Title").or(song.get("file"))?.to_string(),
+                    artist: artist.to_string(),
+                    album: song.get("Album").unwrap_or_default().to_string(),
+                    album_artist: song.get("AlbumArtist").unwrap_or(artist).to_string(),
+                })
+            })
+            .collect())
+    }
+
+    fn remove_from_queue(&self, ids: &[String]) -> Result<()> {
+        // Only remove songs that are still upcoming, in case one started playing since the queue was fetched
+        let (_, upcoming_ids) = self.upcoming_ids()?;
+        let commands: Vec<String> = ids
+            .iter()
+            .filter(|id| upcoming_ids.contains(id))
+            .map(|id| format!("deleteid {id}"))
+            .collect();
+        if !commands.is_empty() {
+            self.request(&format!("command_list_begin\n{}\ncommand_list_end", commands.join("\n")))?;
+        }
+        Ok(())
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        let status = self.request("status")?;
+        let start = upcoming_start(&status);
+        let length = playlist_length(&status);
+        if start < length {
+            self.request(&format!("delete {start}:{length}"))?;
+        }
+        Ok(())
+    }
+
+    fn move_in_queue(&self, ids: &[String], before: Option<&str>) -> Result<()> {
+        let (start, upcoming_ids) = self.upcoming_ids()?;
+        let order = queue_data::move_block(&upcoming_ids, ids, before);
+
+        // Moving each song to its new position in order leaves every song before it in place, so the songs that
+        // are already in the right place can be skipped
+        let commands: Vec<String> = order
+            .into_iter()
+            .enumerate()
+            .skip_while(|(new_i, i)| new_i == i)
+            .map(|(new_i, i)| format!("moveid {} {}", upcoming_ids[i], start + new_i))
+            .collect();
+        if !commands.is_empty() {
+            self.request(&format!("command_list_begin\n{}\ncommand_list_end", commands.join("\n")))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::sync::Arc;
+
+    /// The commands a fake server has received
+    type Commands = Arc<Mutex<Vec<u8>>>;
+
+    /// A scripted MPD server, which replies with canned responses in order and records the commands it's sent
+    struct FakeServer {
+        responses: Cursor<Vec<u8>>,
+        commands: Commands,
+    }
+
+    impl Read for FakeServer {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.responses.read(buf)
+        }
+    }
+
+    impl Write for FakeServer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.commands.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Makes a reader for a server that sends the given responses, along with the commands it receives
+    fn fake_server(responses: &[u8]) -> (BufReader<Box<dyn Stream>>, Commands) {
+        let commands = Arc::new(Mutex::new(Vec::new()));
+        let server = FakeServer {
+            responses: Cursor::new(responses.to_vec()),
+            commands: commands.clone(),
+        };
+        (BufReader::new(Box::new(server)), commands)
+    }
+
+    /// Makes a backend that's already connected to a server that sends the given responses
+    fn fake_backend(responses: &[u8]) -> (MpdBackend, Commands) {
+        let (reader, commands) = fake_server(responses);
+        let backend = MpdBackend {
+            host: DEFAULT_HOST.to_string(),
+            port: DEFAULT_PORT.to_string(),
+            password: None,
+            connection: Mutex::new(Some(reader)),
+            artwork_cache: Mutex::new((String::new(), None)),
+            queued_ids: Mutex::new(HashSet::new()),
+        };
+        (backend, commands)
+    }
+
+    fn response(pairs: &[(&str, &str)]) -> MpdResponse {
+        MpdResponse {
+            pairs: pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            binary: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn parses_pairs() {
+        let (mut reader, commands) = fake_server(b"state: play\nsong: 3\nOK\n");
+        let status = send_command(&mut reader, "status").unwrap();
+        assert_eq!(status.get("state"), Some("play"));
+        // Keys are looked up case-insensitively
+        assert_eq!(status.get("SONG"), Some("3"));
+        assert_eq!(status.get("volume"), None);
+        assert_eq!(commands.lock().unwrap().as_slice(), b"status\n");
+    }
+
+    #[test]
+    fn ack_is_a_script_error() {
+        let (mut reader, _) = fake_server(b"ACK [50@0] {play} No such song\n");
+        match send_command(&mut reader, "play 99") {
+            Err(MicromusicError::Script(message)) => assert_eq!(message, "[50@0] {play} No such song"),
+            _ => panic!("expected a script error"),
+        }
+    }
+
+    #[test]
+    fn closed_connection_is_a_connection_error() {
+        let (mut reader, _) = fake_server(b"state: play\n");
+        assert!(matches!(send_command(&mut reader, "status"), Err(MicromusicError::Connection(_))));
+    }
+
+    #[test]
+    fn reads_binary_chunk() {
+        // The binary data may contain newlines, and is followed by one
+        let (mut reader, _) = fake_server(b"size: 10\nbinary: 4\n\x01\n\x02\x03\nOK\n");
+        let response = send_command(&mut reader, "albumart \"a.flac\" 0").unwrap();
+        assert_eq!(response.get("size"), Some("10"));
+        assert_eq!(response.binary, vec![1, b'\n', 2, 3]);
+    }
+
+    #[test]
+    fn reads_binary_in_chunks() {
+        let (backend, commands) = fake_backend(b"size: 6\nbinary: 3\nabc\nOK\nsize: 6\nbinary: 3\ndef\nOK\n");
+        assert_eq!(backend.read_binary("albumart", "a.flac"), Some(b"abcdef".to_vec()));
+        assert_eq!(
+            commands.lock().unwrap().as_slice(),
+            b"albumart \"a.flac\" 0\nalbumart \"a.flac\" 3\n"
+        );
+    }
+
+    #[test]
+    fn missing_binary_is_none() {
+        let (backend, _) = fake_backend(b"ACK [50@0] {albumart} No file exists\n");
+        assert_eq!(backend.read_binary("albumart", "a.flac"), None);
+        let (backend, _) = fake_backend(b"size: 0\nbinary: 0\n\nOK\n");
+        assert_eq!(backend.read_binary("albumart", "a.flac"), None);
+    }
+
+    #[test]
+    fn splits_songs() {
+        let songs = split_songs(response(&[
+            ("file", "a.flac"),
+            ("Title", "A"),
+            ("Id", "1"),
+            ("file", "b.flac"),
+            ("Id", "2"),
+        ]));
+        assert_eq!(songs.len(), 2);
+        assert_eq!(songs[0].get("Title"), Some("A"));
+        assert_eq!(songs[0].get("Id"), Some("1"));
+        assert_eq!(songs[1].get("file"), Some("b.flac"));
+        assert_eq!(songs[1].get("Title"), None);
+        assert!(split_songs(MpdResponse::default()).is_empty());
+    }
+
+    #[test]
+    fn upcoming_start_follows_current_song() {
+        assert_eq!(upcoming_start(&response(&[("state", "play"), ("song", "2")])), 3);
+        assert_eq!(upcoming_start(&response(&[("state", "pause"), ("song", "0")])), 1);
+        // When stopped, the current song hasn't started yet
+        assert_eq!(upcoming_start(&response(&[("state", "stop"), ("song", "2")])), 2);
+        assert_eq!(upcoming_start(&response(&[("state", "stop")])), 0);
+    }
+
+    #[test]
+    fn quotes_arguments() {
+        assert_eq!(quote(r#"say "hi" \ bye"#), r#""say \"hi\" \\ bye""#);
+    }
+
+    #[test]
+    fn parses_grouped_album_list() {
+        let (backend, commands) = fake_backend(
+            concat!(
+                "AlbumArtist: Aphex Twin\n",
+                "Genre: Electronic\n",
+                "Date: 1994-03-07\n",
+                "Album: Selected Ambient Works Volume II\n",
+                "Date: 2014\n",
+                "Album: Syro\n",
+                "AlbumArtist: Boards of Canada\n",
+                "Genre: \n",
+                "Date: \n",
+                "Album: Geogaddi\n",
+                "Album: Twoism\n",
+                "Album: \n",
+                "OK\n",
+                // Geogaddi's artwork isn't cached, so it's read from its first song
+                "file: boc/geogaddi/01.flac\nOK\n",
+                "size: 3\nbinary: 3\npng\nOK\n",
+                // Twoism has no artwork at all
+                "file: boc/twoism/01.flac\nOK\n",
+                "ACK [50@0] {albumart} No file exists\n",
+                "ACK [50@0] {readpicture} No file exists\n",
+            )
+            .as_bytes(),
+        );
+        let cached = vec![
+            String::from("Aphex TwinSelected Ambient Works Volume II"),
+            String::from("Aphex TwinSyro"),
+        ];
+        let albums = backend.get_album_data(cached).unwrap();
+
+        let summary: Vec<(&str, &str, Option<&str>, Option<u32>)> = albums
+            .iter()
+            .map(|album| (album.album.as_str(), album.album_artist.as_str(), album.genre.as_deref(), album.year))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Selected Ambient Works Volume II", "Aphex Twin", Some("Electronic"), Some(1994)),
+                ("Syro", "Aphex Twin", Some("Electronic"), Some(2014)),
+                ("Geogaddi", "Boards of Canada", None, None),
+            ]
+        );
+        assert_eq!(albums[0].artwork_data, None);
+        assert_eq!(albums[2].artwork_data, Some(b"png".to_vec()));
+        assert!(albums.iter().all(|album| album.play_count.is_none() && album.last_played.is_none()));
+        assert!(String::from_utf8_lossy(&commands.lock().unwrap())
+            .starts_with("list album group albumartist group genre group date\nfind albumartist \"Boards of Canada\""));
+    }
+
+    #[test]
+    fn parses_player_data() {
+        let (backend, commands) = fake_backend(
+            concat!(
+                "volume: 40\nrepeat: 1\nrandom: 1\nsingle: 0\nsong: 1\nstate: play\n",
+                "elapsed: 12.500\nduration: 200.000\nOK\n",
+                "file: boc/mhtrtc/03.flac\nTitle: Roygbiv\nArtist: Boards of Canada\n",
+                "Album: Music Has the Right to Children\nOK\n",
+                "sticker: loved=1\nOK\n",
+                "size: 3\nbinary: 3\npng\nOK\n",
+            )
+            .as_bytes(),
+        );
+        let data = backend.get_player_data().unwrap().unwrap();
+        assert_eq!(data.track_info.name(), "Roygbiv");
+        assert_eq!(data.track_info.artist(), "Boards of Canada");
+        assert_eq!(data.track_info.album(), "Music Has the Right to Children");
+        // Songs without an album artist tag fall back to the artist
+        assert_eq!(data.track_info.album_artist(), "Boards of Canada");
+        assert!(data.track_info.loved());
+        assert_eq!(data.track_info.length(), 200.);
+        assert_eq!(data.player_info.pos(), 12.5);
+        assert!(data.player_info.state() == PlayerState::Playing);
+        assert_eq!(data.player_info.volume(), Some(40));
+        assert!(data.player_info.shuffle());
+        assert!(data.player_info.repeat() == RepeatMode::All);
+        assert!(data.player_info.dj_active());
+        assert_eq!(data.track_artwork_data, Some(b"png".to_vec()));
+        assert_eq!(
+            commands.lock().unwrap().as_slice(),
+            b"status\ncurrentsong\nsticker get song \"boc/mhtrtc/03.flac\" loved\nalbumart \"boc/mhtrtc/03.flac\" 0\n"
+        );
+    }
+
+    #[test]
+    fn stopped_player_has_no_data() {
+        let (backend, _) = fake_backend(b"volume: 40\nstate: stop\nOK\n");
+        assert!(backend.get_player_data().unwrap().is_none());
+    }
+
+    #[test]
+    fn remembers_queued_songs() {
+        let (backend, commands) = fake_backend(
+            concat!(
+                "playlistlength: 2\nstate: play\nOK\n",
+                "OK\n",
+                "playlistlength: 4\nOK\n",
+                "file: a.flac\nId: 10\nfile: b.flac\nId: 11\nOK\n",
+            )
+            .as_bytes(),
+        );
+        backend.queue_album("Geogaddi", "Boards of Canada", true).unwrap();
+        let mut queued_ids: Vec<String> = backend.queued_ids.lock().unwrap().iter().cloned().collect();
+        queued_ids.sort();
+        assert_eq!(queued_ids, vec!["10", "11"]);
+        assert!(String::from_utf8_lossy(&commands.lock().unwrap()).ends_with("status\nplaylistinfo 2:4\n"));
+    }
+
+    #[test]
+    fn clears_only_played_songs_micromusic_queued() {
+        let (backend, commands) = fake_backend(
+            b"repeat: 0\nrandom: 0\nsong: 2\nstate: play\nOK\nfile: a.flac\nId: 5\nfile: b.flac\nId: 7\nOK\nOK\n",
+        );
+        backend.queued_ids.lock().unwrap().extend([String::from("7"), String::from("9")]);
+        backend.clear_played_tracks().unwrap();
+        assert_eq!(
+            commands.lock().unwrap().as_slice(),
+            b"status\nplaylistinfo 0:2\ncommand_list_begin\ndeleteid 7\ncommand_list_end\n"
+        );
+        assert_eq!(backend.queued_ids.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn keeps_played_songs_when_repeating() {
+        let (backend, commands) = fake_backend(b"repeat: 1\nrandom: 0\nsong: 2\nstate: play\nOK\n");
+        backend.queued_ids.lock().unwrap().insert(String::from("5"));
+        backend.clear_played_tracks().unwrap();
+        assert_eq!(commands.lock().unwrap().as_slice(), b"status\n");
+    }
+
+    #[test]
+    fn sets_volume_with_setvol() {
+        let (backend, commands) = fake_backend(b"OK\n");
+        backend.run_command(PlayerCommand::SetVolume(42)).unwrap();
+        assert_eq!(commands.lock().unwrap().as_slice(), b"setvol 42\n");
+    }
+}
@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{Connection, Proxy, ProxyBuilder};
+use zbus::zvariant::{ObjectPath, OwnedValue, Value};
+use zbus::CacheProperties;
+
+use crate::album_data::{AlbumData, AlbumTrack};
+use crate::backend::{PlayerCommand, PlayerBackend};
+use crate::error::{MicromusicError, Result};
+use crate::player_data::{PlayerData, PlayerInfo, PlayerState, RepeatMode, TrackInfo};
+use crate::queue_data::QueuedTrack;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+
+/// A backend that controls any media player implementing MPRIS over the D-Bus session bus. MPRIS has no concept of a
+/// library or playlists, so the album select screen and queue stay empty and queueing does nothing.
+pub struct MprisBackend {
+    connection: Connection,
+}
+
+impl MprisBackend {
+    pub fn new() -> zbus::Result<Self> {
+        Ok(MprisBackend {
+            connection: Connection::session()?,
+        })
+    }
+
+    /// Gets a proxy for the player that should be displayed, preferring one that is currently playing over any
+    /// others. Returns None if no MPRIS players are running.
+    fn player(&self) -> Result<Option<Proxy<'static>>> {
+        let names = DBusProxy::new(&self.connection)
+            .map_err(dbus_error)?
+            .list_names()
+            .map_err(|e| dbus_error(e.into()))?;
+        let players: Vec<Proxy> = names
+            .into_iter()
+            .filter(|name| name.starts_with(MPRIS_PREFIX))
+            .filter_map(|name| {
+                ProxyBuilder::new_bare(&self.connection)
+                    .destination(name.to_string())
+                    .ok()?
+                    .path(MPRIS_PATH)
+                    .ok()?
+                    .interface(MPRIS_PLAYER_INTERFACE)
+                    .ok()?
+                    // Position changes are never signalled, so properties have to be fetched every time
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .ok()
+            })
+            .collect();
+
+        let playing = players.iter().position(|player| {
+            player.get_property::<String>("PlaybackStatus").ok().as_deref() == Some("Playing")
+        });
+        Ok(players.into_iter().nth(playing.unwrap_or(0)))
+    }
+
+    /// Moves to a position in the current track, in seconds. SetPosition is ignored by the player unless it's given
+    /// the ID of the current track, which prevents seeking in a track that has changed in the meantime.
+    fn seek(&self, pos: f64) -> Result<()> {
+        let Some(player) = self.player()? else {
+            return Ok(());
+        };
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").map_err(dbus_error)?;
+        // Some players send the track ID as a string rather than an object path
+        let track_id = match metadata.get("mpris:trackid").map(|value| &**value) {
+            Some(Value::ObjectPath(path)) => path.clone(),
+            Some(Value::Str(s)) => ObjectPath::try_from(s.as_str()).map_err(|e| dbus_error(e.into()))?,
+            _ => return Ok(()),
+        };
+        player
+            .call::<_, _, ()>("SetPosition", &(track_id, (pos * 1_000_000.) as i64))
+            .map_err(dbus_error)
+    }
+
+    /// Sets a property on the active player, if there is one
+    fn set_player_property<'t, T: Into<Value<'t>> + 't>(&self, name: &str, value: T) -> Result<()> {
+        if let Some(player) = self.player()? {
+            player.set_property(name, value).map_err(|e| dbus_error(e.into()))?;
+        }
+        Ok(())
+    }
+}
+
+fn dbus_error(e: zbus::Error) -> MicromusicError {
+    MicromusicError::Script(e.to_string())
+}
+
+/// Gets a string from an MPRIS metadata map. Lists of strings (such as artists) are joined with commas.
+fn metadata_str(metadata: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    match metadata.get(key).map(|value| &**value) {
+        Some(Value::Str(s)) => Some(s.to_string()),
+        Some(Value::Array(array)) => Some(
+            array
+                .get()
+                .iter()
+                .filter_map(|value| match value {
+                    Value::Str(s) => Some(s.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<&str>>()
+                .join(", "),
+        ),
+        _ => None,
+    }
+}
+
+/// Gets a length in microseconds from an MPRIS metadata map and converts it to seconds
+fn metadata_secs(metadata: &HashMap<String, OwnedValue>, key: &str) -> Option<f64> {
+    match metadata.get(key).map(|value| &**value) {
+        Some(Value::I64(us)) => Some(*us as f64 / 1_000_000.),
+        Some(Value::U64(us)) => Some(*us as f64 / 1_000_000.),
+        _ => None,
+    }
+}
+
+/// Reads the track info from an MPRIS metadata map
+fn track_info(metadata: &HashMap<String, OwnedValue>) -> TrackInfo {
+    TrackInfo::new(
+        metadata_str(metadata, "xesam:title").unwrap_or_default(),
+        metadata_str(metadata, "xesam:artist").unwrap_or_default(),
+        metadata_str(metadata, "xesam:album").unwrap_or_default(),
+        metadata_str(metadata, "xesam:albumArtist")
+            .or_else(|| metadata_str(metadata, "xesam:artist"))
+            .unwrap_or_default(),
+        // MPRIS has no equivalent of loving a track
+        false,
+        // Some players don't report a length, so avoid dividing by zero when drawing the progress bar
+        metadata_secs(metadata, "mpris:length").filter(|l| *l > 0.).unwrap_or(1.0),
+    )
+}
+
+/// Converts an MPRIS LoopStatus to a repeat mode. LoopStatus is optional, so players without it are shown with repeat
+/// off.
+fn repeat_mode(loop_status: Option<&str>) -> RepeatMode {
+    match loop_status {
+        Some("Track") => RepeatMode::One,
+        Some("Playlist") => RepeatMode::All,
+        _ => RepeatMode::Off,
+    }
+}
+
+/// Converts a repeat mode to an MPRIS LoopStatus
+fn loop_status(repeat: RepeatMode) -> &'static str {
+    match repeat {
+        RepeatMode::Off => "None",
+        RepeatMode::One => "Track",
+        RepeatMode::All => "Playlist",
+    }
+}
+
+/// Loads the artwork pointed to by an `mpris:artUrl`. Only local files are supported.
+fn load_artwork(art_url: &str) -> Option<Vec<u8>> {
+    let encoded = art_url.strip_prefix("file://")?.as_bytes();
+
+    // Undo the percent-encoding of the file URL
+    let mut path = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let escaped = encoded.get(i + 1..i + 3).filter(|_| encoded[i] == b'%');
+        match escaped.and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok()) {
+            Some(byte) => {
+                path.push(byte);
+                i += 3;
+            }
+            None => {
+                path.push(encoded[i]);
+                i += 1;
+            }
+        }
+    }
+
+    std::fs::read(String::from_utf8(path).ok()?).ok()
+}
+
+impl PlayerBackend for MprisBackend {
+    fn get_player_data(&self) -> Result<Option<PlayerData>> {
+        let Some(player) = self.player()? else {
+            return Ok(None);
+        };
+
+        let state = match player.get_property::<String>("PlaybackStatus").map_err(dbus_error)?.as_str() {
+            "Playing" => PlayerState::Playing,
+            "Paused" => PlayerState::Paused,
+            _ => return Ok(None),
+        };
+        let metadata: HashMap<String, OwnedValue> = player.get_property("Metadata").map_err(dbus_error)?;
+        let pos = player.get_property::<i64>("Position").unwrap_or(0) as f64 / 1_000_000.;
+        // MPRIS volumes go from 0.0 to 1.0, and players without volume control may not have the property at all
+        let volume = player
+            .get_property::<f64>("Volume")
+            .ok()
+            .map(|v| (v.clamp(0., 1.) * 100.).round() as u8);
+        // Shuffle is optional too, so players without it are shown with shuffle off
+        let shuffle = player.get_property::<bool>("Shuffle").unwrap_or(false);
+        let repeat = repeat_mode(player.get_property::<String>("LoopStatus").ok().as_deref());
+
+        let track_artwork_data = metadata_str(&metadata, "mpris:artUrl").and_then(|url| load_artwork(&url));
+
+        Ok(Some(PlayerData {
+            track_info: track_info(&metadata),
+            player_info: PlayerInfo::new(pos, false, state, volume, shuffle, repeat),
+            track_artwork_data,
+        }))
+    }
+
+    fn run_command(&self, command: PlayerCommand) -> Result<()> {
+        let method = match command {
+            PlayerCommand::PlayPause => "PlayPause",
+            PlayerCommand::NextTrack => "Next",
+            PlayerCommand::BackTrack => "Previous",
+            PlayerCommand::Seek(pos) => return self.seek(pos),
+            PlayerCommand::SetVolume(volume) => return self.set_player_property("Volume", volume as f64 / 100.),
+            PlayerCommand::SetShuffle(shuffle) => return self.set_player_property("Shuffle", shuffle),
+            PlayerCommand::SetRepeat(repeat) => return self.set_player_property("LoopStatus", loop_status(repeat)),
+            PlayerCommand::Love | PlayerCommand::Unlove => return Ok(()),
+        };
+        if let Some(player) = self.player()? {
+            player.call::<_, _, ()>(method, &()).map_err(dbus_error)?;
+        }
+        Ok(())
+    }
+
+    fn get_album_data(&self, _cached_albums: Vec<String>) -> Result<Vec<AlbumData>> {
+        Ok(Vec::new())
+    }
+
+    fn queue_album(&self, _album: &str, _album_artist: &str, _start_playback: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn play_album_next(&self, _album: &str, _album_artist: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_album_tracks(&self, _album: &str, _album_artist: &str) -> Result<Vec<AlbumTrack>> {
+        Ok(Vec::new())
+    }
+
+    fn queue_tracks(&self, _ids: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn make_dj_playlist(&self, _keep_tracks: bool) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_played_tracks(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_queue(&self) -> Result<Vec<QueuedTrack>> {
+        Ok(Vec::new())
+    }
+
+    fn remove_from_queue(&self, _ids: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn move_in_queue(&self, _ids: &[String], _before: Option<&str>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(entries: Vec<(&str, Value)>) -> HashMap<String, OwnedValue> {
+        entries.into_iter().map(|(key, value)| (key.to_string(), value.into())).collect()
+    }
+
+    #[test]
+    fn track_info_from_metadata() {
+        let metadata = metadata(vec![
+            ("xesam:title", Value::from("Windowlicker")),
+            ("xesam:artist", Value::from(vec!["Aphex Twin", "AFX"])),
+            ("xesam:album", Value::from("Windowlicker")),
+            ("xesam:albumArtist", Value::from(vec!["Aphex Twin"])),
+            ("mpris:length", Value::from(367_500_000i64)),
+        ]);
+        let info = track_info(&metadata);
+        assert_eq!(info.name(), "Windowlicker");
+        assert_eq!(info.artist(), "Aphex Twin, AFX");
+        assert_eq!(info.album(), "Windowlicker");
+        assert_eq!(info.album_artist(), "Aphex Twin");
+        assert!(!info.loved());
+        assert_eq!(info.length(), 367.5);
+    }
+
+    #[test]
+    fn track_info_accepts_unsigned_length() {
+        let metadata = metadata(vec![("mpris:length", Value::from(2_000_000u64))]);
+        assert_eq!(track_info(&metadata).length(), 2.);
+    }
+
+    #[test]
+    fn track_info_from_empty_metadata() {
+        let info = track_info(&HashMap::new());
+        assert_eq!(info.name(), "");
+        assert_eq!(info.artist(), "");
+        assert_eq!(info.album(), "");
+        assert_eq!(info.album_artist(), "");
+        // A missing or zero length falls back to one second
+        assert_eq!(info.length(), 1.);
+        let metadata = metadata(vec![("mpris:length", Value::from(0i64))]);
+        assert_eq!(track_info(&metadata).length(), 1.);
+    }
+
+    #[test]
+    fn loop_status_to_repeat_mode() {
+        assert!(repeat_mode(Some("None")) == RepeatMode::Off);
+        assert!(repeat_mode(Some("Track")) == RepeatMode::One);
+        assert!(repeat_mode(Some("Playlist")) == RepeatMode::All);
+        assert!(repeat_mode(Some("Shuffle")) == RepeatMode::Off);
+        assert!(repeat_mode(None) == RepeatMode::Off);
+    }
+
+    #[test]
+    fn repeat_mode_round_trips() {
+        for repeat in [RepeatMode::Off, RepeatMode::One, RepeatMode::All] {
+            assert!(repeat_mode(Some(loop_status(repeat))) == repeat);
+        }
+    }
+}
@@ -1,155 +1,219 @@
 use osascript;
 use serde::Serialize;
 
-use std::sync::mpsc::Sender;
-use std::thread;
-use std::time::Duration;
-
-use crate::album_data::ADOsascriptResponse;
-use crate::player_data::PDOsascriptResponse;
-
-type PlayerDataSender = Sender<Option<PDOsascriptResponse>>;
-
-/// Returns information on the state of the music player
-fn get_player_data() -> Option<PDOsascriptResponse> {
-    // TODO: make it so that this is toggled based on OS
-    const PLAYER_DATA_SCRIPT: &'static str = include_str!("osascript_requests/get_player_data.jxa");
-    let script = osascript::JavaScript::new(PLAYER_DATA_SCRIPT);
-    script.execute().ok()
+use crate::album_data::{AlbumData, AlbumTrack};
+use crate::backend::{PlayerCommand, PlayerBackend};
+use crate::error::{MicromusicError, Result};
+use crate::player_data::{PlayerData, RepeatMode};
+use crate::queue_data::{self, QueuedTrack};
+
+/// A backend that controls Apple Music by running JXA scripts with osascript
+pub struct OsascriptBackend {
+    /// The name of the playlist that's used as the micromusic DJ queue
+    playlist_name: String,
 }
 
-/// Sends information about the music player's state to the main thread
-fn send_player_data(data: Option<PDOsascriptResponse>, tx: PlayerDataSender) {
-    tx.send(data)
-        .expect("Couldn't send player data through the channel");
+impl OsascriptBackend {
+    pub fn new(playlist_name: String) -> Self {
+        OsascriptBackend { playlist_name }
+    }
 }
 
-/// Creates a new thread to gather information on the current track and send it to the main thread once complete.
-pub fn send_player_data_async(tx: PlayerDataSender) {
-    thread::spawn(move || {
-        let data = get_player_data();
-        send_player_data(data, tx);
-    });
-}
+/// Thrown by get_player_data.jxa when Music isn't running, so it can be told apart from other script errors
+const NOT_RUNNING_MESSAGE: &str = "micromusic: Music is not running";
 
-/// Periodically runs a JXA script to gather information on the current track and send it to the main thread.
-pub fn send_player_data_loop(tx: PlayerDataSender) {
-    thread::spawn(move || {
-        let mut time_remaining = 3.;
-        loop {
-            let data = get_player_data();
-            if let Some(response) = data.as_ref() {
-                time_remaining = response.track_info.length() - response.player_info.pos();
-            }
-            send_player_data(data, tx.clone());
-            // If the track is almost over, don't sleep the full duration so the info can be updated immediately after it ends
-            thread::sleep(Duration::from_secs_f64(time_remaining.min(3.0).max(0.2)));
+impl PlayerCommand {
+    fn script(&self) -> String {
+        match self {
+            PlayerCommand::PlayPause => String::from("Application('Music').playpause()"),
+            PlayerCommand::NextTrack => String::from("Application('Music').nextTrack()"),
+            PlayerCommand::BackTrack => String::from("Application('Music').backTrack()"),
+            PlayerCommand::Seek(pos) => format!("Application('Music').playerPosition = {pos}"),
+            PlayerCommand::SetVolume(volume) => format!("Application('Music').soundVolume = {volume}"),
+            PlayerCommand::SetShuffle(shuffle) => format!("Application('Music').shuffleEnabled = {shuffle}"),
+            PlayerCommand::SetRepeat(repeat) => format!(
+                "Application('Music').songRepeat = '{}'",
+                match repeat {
+                    RepeatMode::Off => "off",
+                    RepeatMode::One => "one",
+                    RepeatMode::All => "all",
+                }
+            ),
+            // PlayerCommand::Love => "Application('Music').currentTrack.loved = true",
+            // PlayerCommand::Unlove => "Application('Music').currentTrack.loved = false",
+            PlayerCommand::Love => String::from("try { Application('Music').currentTrack.loved = true } catch { Application('Music').currentTrack.favorited = true }"),
+            PlayerCommand::Unlove => String::from("try { Application('Music').currentTrack.loved = false } catch { Application('Music').currentTrack.favorited = false }"),
         }
-    });
+    }
 }
 
-/// Clears all played tracks from the micromusic DJ playlist. Does nothing if there is an error.
-fn clear_played_tracks() {
-    const CLEAR_PLAYED_SCRIPT: &'static str = include_str!("osascript_requests/clear_played_tracks.jxa");
-    let script = osascript::JavaScript::new(CLEAR_PLAYED_SCRIPT);
-    let _: () = script.execute().unwrap_or_else(|e| println!("Unable to clear played tracks: {e}"));
+#[derive(Serialize)]
+struct GetAlbumDataParams {
+    cached_albums: Vec<String>,
 }
 
-/// Periodically runs a JXA script to clear played tracks from the micromusic DJ playlist
-pub fn clear_played_tracks_loop() {
-    thread::spawn(move || {
-        loop {
-            clear_played_tracks();
-            // If the track is almost over, don't sleep the full duration so the info can be updated immediately after it ends
-            thread::sleep(Duration::from_secs_f64(10.0));
-        }
-    });
+#[derive(Serialize)]
+struct PlayAlbumParams<'a> {
+    album: &'a str,
+    album_artist: &'a str,
+    playlist_name: &'a str,
 }
 
-#[allow(dead_code)]
-pub enum JXACommand {
-    PlayPause,
-    NextTrack,
-    PreviousTrack,
-    BackTrack,
-    Love,
-    Unlove,
+#[derive(Serialize)]
+struct QueueAlbumParams<'a> {
+    album: &'a str,
+    album_artist: &'a str,
+    start_playback: bool,
+    playlist_name: &'a str,
 }
 
-impl JXACommand {
-    fn as_str(&self) -> &'static str {
-        match self {
-            JXACommand::PlayPause => "Application('Music').playpause()",
-            JXACommand::NextTrack => "Application('Music').nextTrack()",
-            JXACommand::PreviousTrack => "Application('Music').previousTrack()",
-            JXACommand::BackTrack => "Application('Music').backTrack()",
-            // JXACommand::Love => "Application('Music').currentTrack.loved = true",
-            // JXACommand::Unlove => "Application('Music').currentTrack.loved = false",
-            JXACommand::Love => "try { Application('Music').currentTrack.loved = true } catch { Application('Music').currentTrack.favorited = true }", 
-            JXACommand::Unlove => "try { Application('Music').currentTrack.loved = false } catch { Application('Music').currentTrack.favorited = false }", 
-        }
-    }
+#[derive(Serialize)]
+struct AlbumParams<'a> {
+    album: &'a str,
+    album_artist: &'a str,
 }
 
-/// Can run different JXA commands depending on the "command" parameter
-/// * `tx` - An MPSC sender to optionally update the player data on the main thread after the command has completed. Set it to None to disable this behavior.
-pub fn run_command<T>(command: JXACommand, tx: T)
-where
-    T: Into<Option<PlayerDataSender>>,
-{
-    let tx = tx.into();
-    let script = osascript::JavaScript::new(command.as_str());
-    thread::spawn(move || {
-        let _: () = script.execute().unwrap();
-        if let Some(tx) = tx {
-            send_player_data_async(tx);
-        }
-    });
+#[derive(Serialize)]
+struct QueueTracksParams<'a> {
+    ids: &'a [String],
+    playlist_name: &'a str,
 }
 
 #[derive(Serialize)]
-struct GetAlbumDataParams {
-    cached_albums: Vec<String>,
+struct PlaylistParams<'a> {
+    playlist_name: &'a str,
 }
 
-/// Gets data for the album selection screen. Should only be run one time at the start of the program.
-pub fn get_album_data(album_cache: Vec<String>) -> Vec<ADOsascriptResponse> {
-    const ALBUM_DATA_SCRIPT: &'static str = include_str!("osascript_requests/get_album_data.jxa");
-    let script = osascript::JavaScript::new(ALBUM_DATA_SCRIPT);
-    let result: Vec<ADOsascriptResponse> = script
-        .execute_with_params(GetAlbumDataParams {
-            cached_albums: album_cache,
-        })
-        .unwrap();
-    result
+#[derive(Serialize)]
+struct MakeDjPlaylistParams<'a> {
+    keep_tracks: bool,
+    playlist_name: &'a str,
 }
 
 #[derive(Serialize)]
-struct PlayAlbumParams {
-    album: String,
-    album_artist: String,
+struct RemoveFromQueueParams<'a> {
+    ids: &'a [String],
+    playlist_name: &'a str,
 }
 
-pub fn queue_album(album: String, album_artist: String) {
-    const ALBUM_PLAY_SCRIPT: &'static str = include_str!("osascript_requests/queue_album.jxa");
-    let script = osascript::JavaScript::new(ALBUM_PLAY_SCRIPT);
+#[derive(Serialize)]
+struct MoveInQueueParams<'a> {
+    /// The IDs of the upcoming tracks when the new order was worked out
+    upcoming_ids: &'a [String],
+    /// The new order of the upcoming tracks, as indices into `upcoming_ids`
+    order: &'a [usize],
+    playlist_name: &'a str,
+}
 
-    thread::spawn(move || {
-        let _: () = script
-            .execute_with_params(PlayAlbumParams {
-                album,
-                album_artist,
+impl PlayerBackend for OsascriptBackend {
+    /// Returns information on the state of the music player
+    fn get_player_data(&self) -> Result<Option<PlayerData>> {
+        const PLAYER_DATA_SCRIPT: &str = include_str!("osascript_requests/get_player_data.jxa");
+        let script = osascript::JavaScript::new(PLAYER_DATA_SCRIPT);
+        script
+            .execute_with_params(PlaylistParams { playlist_name: &self.playlist_name })
+            .map_err(|e| match e {
+                osascript::Error::Script(message) if message.contains(NOT_RUNNING_MESSAGE) => {
+                    MicromusicError::NotRunning
+                }
+                e => e.into(),
             })
-            .unwrap();
-    });
-}
+    }
 
-pub fn make_dj_playlist() {
-    const MAKE_DJ_PLAYLIST_SCRIPT: &'static str =
-        include_str!("osascript_requests/make_dj_playlist.jxa");
-    let script = osascript::JavaScript::new(MAKE_DJ_PLAYLIST_SCRIPT);
-    thread::spawn(move || {
-        let _: () = script.execute().unwrap();
-    });
-}
+    fn run_command(&self, command: PlayerCommand) -> Result<()> {
+        let script = osascript::JavaScript::new(&command.script());
+        Ok(script.execute()?)
+    }
+
+    /// Gets data for the album selection screen. Should only be run one time at the start of the program.
+    fn get_album_data(&self, cached_albums: Vec<String>) -> Result<Vec<AlbumData>> {
+        const ALBUM_DATA_SCRIPT: &str = include_str!("osascript_requests/get_album_data.jxa");
+        let script = osascript::JavaScript::new(ALBUM_DATA_SCRIPT);
+        Ok(script.execute_with_params(GetAlbumDataParams { cached_albums })?)
+    }
+
+    fn queue_album(&self, album: &str, album_artist: &str, start_playback: bool) -> Result<()> {
+        const ALBUM_PLAY_SCRIPT: &str = include_str!("osascript_requests/queue_album.jxa");
+        let script = osascript::JavaScript::new(ALBUM_PLAY_SCRIPT);
+        Ok(script.execute_with_params(QueueAlbumParams {
+            album,
+            album_artist,
+            start_playback,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
+
+    fn play_album_next(&self, album: &str, album_artist: &str) -> Result<()> {
+        const PLAY_ALBUM_NEXT_SCRIPT: &str = include_str!("osascript_requests/play_album_next.jxa");
+        let script = osascript::JavaScript::new(PLAY_ALBUM_NEXT_SCRIPT);
+        Ok(script.execute_with_params(PlayAlbumParams {
+            album,
+            album_artist,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
+
+    fn get_album_tracks(&self, album: &str, album_artist: &str) -> Result<Vec<AlbumTrack>> {
+        const GET_ALBUM_TRACKS_SCRIPT: &str = include_str!("osascript_requests/get_album_tracks.jxa");
+        let script = osascript::JavaScript::new(GET_ALBUM_TRACKS_SCRIPT);
+        Ok(script.execute_with_params(AlbumParams { album, album_artist })?)
+    }
+
+    fn queue_tracks(&self, ids: &[String]) -> Result<()> {
+        const QUEUE_TRACKS_SCRIPT: &str = include_str!("osascript_requests/queue_tracks.jxa");
+        let script = osascript::JavaScript::new(QUEUE_TRACKS_SCRIPT);
+        Ok(script.execute_with_params(QueueTracksParams {
+            ids,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
 
+    fn make_dj_playlist(&self, keep_tracks: bool) -> Result<()> {
+        const MAKE_DJ_PLAYLIST_SCRIPT: &str = include_str!("osascript_requests/make_dj_playlist.jxa");
+        let script = osascript::JavaScript::new(MAKE_DJ_PLAYLIST_SCRIPT);
+        Ok(script.execute_with_params(MakeDjPlaylistParams {
+            keep_tracks,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
+
+    /// Clears all played tracks from the micromusic DJ playlist
+    fn clear_played_tracks(&self) -> Result<()> {
+        const CLEAR_PLAYED_SCRIPT: &str = include_str!("osascript_requests/clear_played_tracks.jxa");
+        let script = osascript::JavaScript::new(CLEAR_PLAYED_SCRIPT);
+        Ok(script.execute_with_params(PlaylistParams { playlist_name: &self.playlist_name })?)
+    }
+
+    fn get_queue(&self) -> Result<Vec<QueuedTrack>> {
+        const GET_QUEUE_SCRIPT: &str = include_str!("osascript_requests/get_queue.jxa");
+        let script = osascript::JavaScript::new(GET_QUEUE_SCRIPT);
+        Ok(script.execute_with_params(PlaylistParams { playlist_name: &self.playlist_name })?)
+    }
+
+    fn remove_from_queue(&self, ids: &[String]) -> Result<()> {
+        const REMOVE_FROM_QUEUE_SCRIPT: &str = include_str!("osascript_requests/remove_from_queue.jxa");
+        let script = osascript::JavaScript::new(REMOVE_FROM_QUEUE_SCRIPT);
+        Ok(script.execute_with_params(RemoveFromQueueParams {
+            ids,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
+
+    fn clear_queue(&self) -> Result<()> {
+        const CLEAR_QUEUE_SCRIPT: &str = include_str!("osascript_requests/clear_queue.jxa");
+        let script = osascript::JavaScript::new(CLEAR_QUEUE_SCRIPT);
+        Ok(script.execute_with_params(PlaylistParams { playlist_name: &self.playlist_name })?)
+    }
+
+    fn move_in_queue(&self, ids: &[String], before: Option<&str>) -> Result<()> {
+        const MOVE_IN_QUEUE_SCRIPT: &str = include_str!("osascript_requests/move_in_queue.jxa");
+        let upcoming_ids: Vec<String> = self.get_queue()?.into_iter().map(|track| track.id).collect();
+        let order = queue_data::move_block(&upcoming_ids, ids, before);
+        let script = osascript::JavaScript::new(MOVE_IN_QUEUE_SCRIPT);
+        Ok(script.execute_with_params(MoveInQueueParams {
+            upcoming_ids: &upcoming_ids,
+            order: &order,
+            playlist_name: &self.playlist_name,
+        })?)
+    }
+}
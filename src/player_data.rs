@@ -4,13 +4,15 @@ use sdl2::render::Texture;
 use sdl2::render::TextureCreator;
 
 use sdl2::video::WindowContext;
-use serde::Deserialize;
+use hex::FromHex;
+use serde::{Deserialize, Deserializer};
 
 #[derive(Deserialize)]
-pub struct PDOsascriptResponse {
+pub struct PlayerData {
     pub track_info: TrackInfo,
     pub player_info: PlayerInfo,
-    pub track_artwork_data: String,
+    #[serde(deserialize_with = "deserialize_optional_raw_data")]
+    pub track_artwork_data: Option<Vec<u8>>,
 }
 
 #[derive(Clone, Copy, Deserialize, PartialEq)]
@@ -22,16 +24,31 @@ pub enum PlayerState {
     Rewinding,
 }
 
-/// Information about a track, including name, artist, album, loved, and length.
+/// The player's repeat mode
+#[derive(Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RepeatMode {
+    Off,
+    /// Repeats the current track
+    One,
+    /// Repeats the whole playlist
+    All,
+}
+
+/// Information about a track, including name, artist, album, album artist, loved, and length.
 #[derive(Deserialize, PartialEq)]
 pub struct TrackInfo {
     name: String,
     artist: String,
     album: String,
+    album_artist: String,
     loved: bool,
     length: f64,
 }
 impl TrackInfo {
+    pub fn new(name: String, artist: String, album: String, album_artist: String, loved: bool, length: f64) -> Self {
+        TrackInfo { name, artist, album, album_artist, loved, length }
+    }
     pub fn name(&self) -> &str {
         return &self.name;
     }
@@ -41,6 +58,9 @@ impl TrackInfo {
     pub fn album(&self) -> &str {
         return &self.album;
     }
+    pub fn album_artist(&self) -> &str {
+        &self.album_artist
+    }
     pub fn loved(&self) -> bool {
         return self.loved;
     }
@@ -49,21 +69,54 @@ impl TrackInfo {
     }
 }
 
-/// Information about the player itself, including the player position (time elapsed in current song) and state.
+/// Information about the player itself, including the player position (time elapsed in current song), state,
+/// volume, and shuffle and repeat modes.
 #[derive(Deserialize, Copy, Clone)]
 pub struct PlayerInfo {
     pos: f64,
     dj_active: bool,
     state: PlayerState,
+    /// The volume from 0 to 100, or None if the player's volume can't be controlled
+    #[serde(default)]
+    volume: Option<u8>,
+    shuffle: bool,
+    repeat: RepeatMode,
 }
 impl PlayerInfo {
+    pub fn new(
+        pos: f64,
+        dj_active: bool,
+        state: PlayerState,
+        volume: Option<u8>,
+        shuffle: bool,
+        repeat: RepeatMode,
+    ) -> Self {
+        PlayerInfo { pos, dj_active, state, volume, shuffle, repeat }
+    }
     pub fn dj_active(&self) -> bool { return self.dj_active; }
     pub fn pos(&self) -> f64 {
         return self.pos;
     }
+    /// Sets the player position locally, e.g. after seeking, so it's shown before the player has responded
+    pub fn set_pos(&mut self, pos: f64) {
+        self.pos = pos;
+    }
     pub fn state(&self) -> PlayerState {
         return self.state;
     }
+    pub fn volume(&self) -> Option<u8> {
+        self.volume
+    }
+    /// Sets the volume locally, e.g. after scrolling, so it's shown before the player has responded
+    pub fn set_volume(&mut self, volume: u8) {
+        self.volume = Some(volume);
+    }
+    pub fn shuffle(&self) -> bool {
+        self.shuffle
+    }
+    pub fn repeat(&self) -> RepeatMode {
+        self.repeat
+    }
 }
 
 /// Computation-heavy texture resources for a track, including a description and artwork texture. Should only be created when a track
@@ -78,9 +131,9 @@ pub struct TrackResources<'a> {
 impl<'a> TrackResources<'a> {
     // TODO: find a better way to determine foreground and background color for the texture than passing them as parameters to this function
     pub fn new<T: 'a>(
-        response: &PDOsascriptResponse,
+        response: &PlayerData,
         texture_creator: &'a TextureCreator<T>,
-    ) -> Result<TrackResources<'a>, Box<dyn std::error::Error>> {
+    ) -> TrackResources<'a> {
         //Create a texture from the album info
         let track_info = &response.track_info;
         let info_texture = crate::engine::text_to_texture(
@@ -93,17 +146,29 @@ impl<'a> TrackResources<'a> {
             Color::RGB(0, 0, 0),
         );
 
-        let artwork_texture = crate::engine::raw_to_texture(&response.track_artwork_data, texture_creator)?;
+        // Fall back to the placeholder artwork if the track doesn't have any or it can't be decoded
+        let artwork_texture = match &response.track_artwork_data {
+            Some(bytes) => crate::engine::raw_to_texture(bytes, texture_creator).unwrap_or_else(|e| {
+                println!("{e}");
+                Self::placeholder_artwork(texture_creator)
+            }),
+            None => Self::placeholder_artwork(texture_creator),
+        };
 
-        Ok(TrackResources {
+        TrackResources {
             info_texture,
             artwork_texture,
-        })
+        }
+    }
+
+    fn placeholder_artwork<T: 'a>(texture_creator: &'a TextureCreator<T>) -> Texture<'a> {
+        const PLACEHOLDER_TEX: &[u8] = include_bytes!("../assets/placeholder.png");
+        texture_creator.load_texture_bytes(&PLACEHOLDER_TEX).unwrap() // Should never panic
     }
 
     pub fn placeholder<T: 'a>(
         texture_creator: &'a TextureCreator<T>,
-    ) -> Result<TrackResources<'a>, Box<dyn std::error::Error>> {
+    ) -> TrackResources<'a> {
         //Create a placeholder info texture
         let info_texture = crate::engine::text_to_texture(
             "        not playing",
@@ -112,13 +177,12 @@ impl<'a> TrackResources<'a> {
             Color::RGB(0, 0, 0),
         );
 
-        const PLACEHOLDER_TEX: &'static [u8] = include_bytes!("../assets/placeholder.png");
-        let artwork_texture = texture_creator.load_texture_bytes(&PLACEHOLDER_TEX).unwrap();
+        let artwork_texture = Self::placeholder_artwork(texture_creator);
 
-        Ok(TrackResources {
+        TrackResources {
             info_texture,
             artwork_texture,
-        })
+        }
     }
 
     pub fn info_texture(&self) -> &Texture {
@@ -129,7 +193,7 @@ impl<'a> TrackResources<'a> {
     }
 }
 
-/// A collection of resources representing all of the data received in and parsed from the original OsascriptResponse.
+/// A collection of resources representing all of the data received in and parsed from the original PlayerData.
 pub struct NowPlayingResourceCollection<'a> {
     pub player_info: PlayerInfo,
     pub track_info: TrackInfo,
@@ -138,21 +202,28 @@ pub struct NowPlayingResourceCollection<'a> {
 
 impl<'a> NowPlayingResourceCollection<'a> {
     pub fn build(
-        response: Option<PDOsascriptResponse>,
+        response: Option<PlayerData>,
         texture_creator: &'a TextureCreator<WindowContext>,
     ) -> NowPlayingResourceCollection<'a> {
         if let Some(response) = response {
-            let track_resources = TrackResources::new(&response, texture_creator).unwrap();
+            let track_resources = TrackResources::new(&response, texture_creator);
             NowPlayingResourceCollection {
                 player_info: response.player_info,
                 track_info: response.track_info,
                 track_resources,
             }
         } else {
-            let track_resources =  TrackResources::placeholder(texture_creator).unwrap();
+            let track_resources = TrackResources::placeholder(texture_creator);
             NowPlayingResourceCollection {
-                player_info: PlayerInfo { pos: 0.0, dj_active: false, state: PlayerState::Stopped },
-                track_info: TrackInfo { name: "".to_string(), artist: "".to_string(), album: "".to_string(), loved: false, length: 1.0 },
+                player_info: PlayerInfo {
+                    pos: 0.0,
+                    dj_active: false,
+                    state: PlayerState::Stopped,
+                    volume: None,
+                    shuffle: false,
+                    repeat: RepeatMode::Off,
+                },
+                track_info: TrackInfo { name: "".to_string(), artist: "".to_string(), album: "".to_string(), album_artist: "".to_string(), loved: false, length: 1.0 },
                 track_resources,
             }
         }
@@ -160,7 +231,7 @@ impl<'a> NowPlayingResourceCollection<'a> {
 
     pub fn update(
         &mut self,
-        response: Option<PDOsascriptResponse>,
+        response: Option<PlayerData>,
         texture_creator: &'a TextureCreator<WindowContext>,
     ) {
         // Determine whether track resources need to be recreated by comparing old player data with new player data
@@ -174,3 +245,22 @@ impl<'a> NowPlayingResourceCollection<'a> {
         *self = Self::build(response, texture_creator);
     }
 }
+
+/// Decodes the hex-encoded raw data that JXA returns for artworks into bytes
+fn raw_to_bytes(raw_data: &str) -> std::result::Result<Vec<u8>, hex::FromHexError> {
+    let hex = raw_data
+        .get(8..raw_data.len().saturating_sub(2))
+        .ok_or(hex::FromHexError::InvalidStringLength)?;
+    Vec::from_hex(hex)
+}
+
+/// Deserializes raw artwork data received from JXA, which may be null if there is no artwork or it has already been
+/// cached
+pub fn deserialize_optional_raw_data<'de, D>(deserializer: D) -> std::result::Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|raw_data| raw_to_bytes(&raw_data).map_err(serde::de::Error::custom))
+        .transpose()
+}
@@ -0,0 +1,168 @@
+use serde::Deserialize;
+
+/// A track in the micromusic DJ queue that hasn't been played yet
+#[derive(Deserialize, Clone)]
+pub struct QueuedTrack {
+    /// Identifies the track to the backend so it can be removed from the queue
+    pub id: String,
+    pub name: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+}
+
+/// A run of consecutive tracks in the queue from the same album, which is shown as a single entry with the tracks
+/// listed below it
+pub struct QueuedAlbum {
+    pub album: String,
+    pub album_artist: String,
+    pub tracks: Vec<QueuedTrack>,
+}
+
+impl QueuedAlbum {
+    /// Groups the tracks in the queue by album, keeping them in order. An album that was queued twice in a row is
+    /// shown as one entry.
+    pub fn group(tracks: Vec<QueuedTrack>) -> Vec<QueuedAlbum> {
+        let mut albums: Vec<QueuedAlbum> = Vec::new();
+        for track in tracks {
+            match albums.last_mut() {
+                Some(last) if last.album == track.album && last.album_artist == track.album_artist => {
+                    last.tracks.push(track)
+                }
+                _ => albums.push(QueuedAlbum {
+                    album: track.album.clone(),
+                    album_artist: track.album_artist.clone(),
+                    tracks: vec![track],
+                }),
+            }
+        }
+        albums
+    }
+
+    /// The IDs of all of the album's tracks, for removing the whole album from the queue
+    pub fn ids(&self) -> Vec<String> {
+        self.tracks.iter().map(|track| track.id.clone()).collect()
+    }
+}
+
+/// Works out the order of the upcoming tracks after moving the tracks with the given IDs as a block, so that they're
+/// played right before the track with the ID `before`, or after every other track if it's None. Returns the new order
+/// as indices into `upcoming`. Like when removing tracks, the first occurrence of each ID is the one that's moved.
+pub fn move_block(upcoming: &[String], ids: &[String], before: Option<&str>) -> Vec<usize> {
+    let mut block: Vec<usize> = Vec::new();
+    for id in ids {
+        if let Some(i) = (0..upcoming.len()).find(|i| upcoming[*i] == *id && !block.contains(i)) {
+            block.push(i);
+        }
+    }
+
+    let mut order: Vec<usize> = (0..upcoming.len()).filter(|i| !block.contains(i)).collect();
+    let insert_at = before
+        .and_then(|before| order.iter().position(|i| upcoming[*i] == before))
+        .unwrap_or(order.len());
+    order.splice(insert_at..insert_at, block);
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(id: &str, album: &str) -> QueuedTrack {
+        QueuedTrack {
+            id: id.to_string(),
+            name: format!("Track {id}"),
+            artist: String::from("Artist"),
+            album: album.to_string(),
+            album_artist: String::from("Artist"),
+        }
+    }
+
+    /// The album and track IDs of each group
+    fn groups(tracks: Vec<QueuedTrack>) -> Vec<(String, Vec<String>)> {
+        QueuedAlbum::group(tracks)
+            .into_iter()
+            .map(|album| (album.album.clone(), album.ids()))
+            .collect()
+    }
+
+    fn group(album: &str, ids: &[&str]) -> (String, Vec<String>) {
+        (album.to_string(), ids.iter().map(|id| id.to_string()).collect())
+    }
+
+    #[test]
+    fn groups_consecutive_tracks() {
+        let tracks = vec![track("1", "A"), track("2", "A"), track("3", "B"), track("4", "B"), track("5", "B")];
+        assert_eq!(groups(tracks), vec![group("A", &["1", "2"]), group("B", &["3", "4", "5"])]);
+        assert!(groups(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn repeated_album_is_grouped_once_in_a_row() {
+        // An album queued twice in a row is one entry, but it's a separate entry when something else is in between
+        let tracks = vec![track("1", "A"), track("2", "A"), track("3", "A"), track("4", "A")];
+        assert_eq!(groups(tracks), vec![group("A", &["1", "2", "3", "4"])]);
+        let tracks = vec![track("1", "A"), track("2", "B"), track("3", "A")];
+        assert_eq!(groups(tracks), vec![group("A", &["1"]), group("B", &["2"]), group("A", &["3"])]);
+    }
+
+    #[test]
+    fn interleaved_tracks_are_separate_entries() {
+        let tracks = vec![track("1", "A"), track("2", "B"), track("3", "A"), track("4", "B")];
+        assert_eq!(
+            groups(tracks),
+            vec![group("A", &["1"]), group("B", &["2"]), group("A", &["3"]), group("B", &["4"])]
+        );
+    }
+
+    #[test]
+    fn albums_with_the_same_title_are_separate() {
+        let mut other = track("2", "A");
+        other.album_artist = String::from("Someone Else");
+        let albums = QueuedAlbum::group(vec![track("1", "A"), other, track("3", "A")]);
+        let artists: Vec<&str> = albums.iter().map(|album| album.album_artist.as_str()).collect();
+        assert_eq!(artists, vec!["Artist", "Someone Else", "Artist"]);
+    }
+
+    fn ids(ids: &str) -> Vec<String> {
+        ids.chars().map(String::from).collect()
+    }
+
+    #[test]
+    fn moves_block_up() {
+        assert_eq!(move_block(&ids("abcde"), &ids("de"), Some("b")), vec![0, 3, 4, 1, 2]);
+        assert_eq!(move_block(&ids("abcde"), &ids("c"), Some("a")), vec![2, 0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn moves_block_down_past_itself() {
+        assert_eq!(move_block(&ids("abcde"), &ids("ab"), Some("e")), vec![2, 3, 0, 1, 4]);
+        assert_eq!(move_block(&ids("abcde"), &ids("b"), Some("d")), vec![0, 2, 1, 3, 4]);
+    }
+
+    #[test]
+    fn moves_block_to_end() {
+        assert_eq!(move_block(&ids("abcde"), &ids("bc"), None), vec![0, 3, 4, 1, 2]);
+    }
+
+    #[test]
+    fn no_op_moves_keep_order() {
+        // Moving a block to right before the track after it, or the last block to the end, leaves it in place
+        assert_eq!(move_block(&ids("abcde"), &ids("bc"), Some("d")), vec![0, 1, 2, 3, 4]);
+        assert_eq!(move_block(&ids("abcde"), &ids("de"), None), vec![0, 1, 2, 3, 4]);
+        assert_eq!(move_block(&ids("abcde"), &[], Some("c")), vec![0, 1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn moves_first_occurrence_of_each_id() {
+        assert_eq!(move_block(&ids("aba"), &ids("a"), None), vec![1, 2, 0]);
+        assert_eq!(move_block(&ids("abab"), &ids("ab"), None), vec![2, 3, 0, 1]);
+    }
+
+    #[test]
+    fn ignores_missing_ids() {
+        // IDs that are no longer upcoming aren't moved, and a missing `before` moves the block to the end
+        assert_eq!(move_block(&ids("abc"), &ids("xb"), Some("a")), vec![1, 0, 2]);
+        assert_eq!(move_block(&ids("abc"), &ids("a"), Some("x")), vec![1, 2, 0]);
+    }
+}
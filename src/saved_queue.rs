@@ -0,0 +1,68 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+use crate::queue_data::{QueuedAlbum, QueuedTrack};
+
+/// An album in the saved queue, identified by the same keys that are used to queue it
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+pub struct SavedAlbum {
+    pub album: String,
+    pub album_artist: String,
+}
+
+impl SavedAlbum {
+    /// Gets the albums in the queue, in the order they'll be played
+    pub fn from_queue(tracks: &[QueuedTrack]) -> Vec<SavedAlbum> {
+        QueuedAlbum::group(tracks.to_vec())
+            .into_iter()
+            .map(|album| SavedAlbum {
+                album: album.album,
+                album_artist: album.album_artist,
+            })
+            .collect()
+    }
+}
+
+/// The albums left in the micromusic DJ queue the last time micromusic was closed, so that they can be queued again on
+/// launch. Saved as `queue.json` in the data directory (e.g. ~/Library/Application Support/com.breitnw.micromusic/).
+#[derive(Serialize, Deserialize)]
+pub struct SavedQueue {
+    /// The backend the queue was saved from, since the albums might not exist in other players' libraries
+    backend: String,
+    pub albums: Vec<SavedAlbum>,
+}
+
+impl SavedQueue {
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "breitnw", "micromusic")
+            .map(|project_dirs| project_dirs.data_dir().join("queue.json"))
+    }
+
+    pub fn new(backend: &str, albums: Vec<SavedAlbum>) -> Self {
+        SavedQueue {
+            backend: backend.to_string(),
+            albums,
+        }
+    }
+
+    /// Loads the queue that was saved from the given backend, or returns None if there isn't one or it can't be read
+    pub fn load(backend: &str) -> Option<Self> {
+        let file = std::fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&file)
+            .map_err(|e| println!("Ignoring invalid saved queue: {e}"))
+            .ok()
+            .filter(|queue: &SavedQueue| queue.backend == backend && !queue.albums.is_empty())
+    }
+
+    /// Saves the queue so it can be restored the next time micromusic is launched
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("Unable to get path to data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| format!("Unable to write {}: {e}", path.display()))
+    }
+}
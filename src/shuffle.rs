@@ -0,0 +1,117 @@
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::album_data::AlbumResources;
+
+/// How many days without being played it takes for an album's weight to stop growing
+const MAX_DAYS_UNPLAYED: f64 = 365.;
+
+/// The current time in seconds since the Unix epoch, for weighting albums by when they were last played
+pub fn now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0., |duration| duration.as_secs_f64())
+}
+
+/// How likely an album is to come up early in a weighted shuffle. Albums that were played long ago (or never) weigh
+/// more, and so do albums with fewer plays. `last_played` and `now` are in seconds since the Unix epoch.
+fn weight(play_count: Option<u32>, last_played: Option<f64>, now: f64) -> f64 {
+    let days_unplayed = last_played
+        .map_or(MAX_DAYS_UNPLAYED, |last_played| (now - last_played) / 86400.)
+        .clamp(0., MAX_DAYS_UNPLAYED);
+    let play_count = play_count.unwrap_or(0) as f64;
+    (1. + days_unplayed / 30.) / (1. + play_count.ln_1p())
+}
+
+/// Shuffles items so that the ones with more weight tend to come first
+fn weighted_shuffle<T>(items: &mut Vec<T>, weight: impl Fn(&T) -> f64, rng: &mut impl Rng) {
+    // Weighted random sampling without replacement (Efraimidis and Spirakis): each item gets an exponentially
+    // distributed key scaled by its weight, and sorting by key gives the shuffled order
+    let mut keyed: Vec<(f64, T)> = items
+        .drain(..)
+        .map(|item| {
+            let u: f64 = rng.gen_range(f64::EPSILON..1.);
+            (-u.ln() / weight(&item), item)
+        })
+        .collect();
+    keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+}
+
+/// Shuffles the albums for the album select screen, either uniformly or weighted so that neglected albums tend to
+/// come first. `now` is in seconds since the Unix epoch.
+pub fn shuffle_albums(albums: &mut Vec<Rc<AlbumResources>>, weighted: bool, now: f64, rng: &mut impl Rng) {
+    if weighted {
+        weighted_shuffle(albums, |album| weight(album.play_count(), album.last_played(), now), rng);
+    } else {
+        albums.shuffle(rng);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    const NOW: f64 = 1_700_000_000.;
+    const DAY: f64 = 86400.;
+
+    /// A play count and last played time, standing in for an album
+    type Plays = (Option<u32>, Option<f64>);
+
+    fn shuffled(albums: &[Plays], rng: &mut StdRng) -> Vec<Plays> {
+        let mut albums = albums.to_vec();
+        weighted_shuffle(&mut albums, |&(play_count, last_played)| weight(play_count, last_played, NOW), rng);
+        albums
+    }
+
+    #[test]
+    fn neglected_albums_weigh_more() {
+        let played_today = weight(Some(5), Some(NOW), NOW);
+        assert!(weight(Some(5), Some(NOW - 60. * DAY), NOW) > played_today);
+        assert!(weight(Some(50), Some(NOW), NOW) < played_today);
+        assert!(weight(None, None, NOW) > weight(Some(5), Some(NOW - 60. * DAY), NOW));
+        // The time since the last play stops counting after a year, and plays from the future count as today
+        assert_eq!(weight(None, Some(NOW - 1000. * DAY), NOW), weight(None, None, NOW));
+        assert_eq!(weight(Some(5), Some(NOW + DAY), NOW), played_today);
+    }
+
+    #[test]
+    fn neglected_albums_come_first_more_often() {
+        let neglected: Plays = (Some(1), Some(NOW - 300. * DAY));
+        let favorite: Plays = (Some(200), Some(NOW - DAY));
+        let albums = [favorite, favorite, neglected, favorite, favorite];
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut neglected_first = 0;
+        let mut neglected_position = 0;
+        for _ in 0..1000 {
+            let order = shuffled(&albums, &mut rng);
+            let position = order.iter().position(|album| *album == neglected).unwrap();
+            neglected_position += position;
+            if position == 0 {
+                neglected_first += 1;
+            }
+        }
+        // A uniform shuffle would put it first a fifth of the time, at position 2 on average
+        assert!(neglected_first > 500, "neglected album came first {neglected_first} times");
+        assert!(neglected_position < 1000, "neglected album's positions added up to {neglected_position}");
+    }
+
+    #[test]
+    fn fixed_seed_gives_fixed_order() {
+        let albums: Vec<Plays> = (0..20).map(|i| (Some(i), Some(NOW - i as f64 * 10. * DAY))).collect();
+        let first = shuffled(&albums, &mut StdRng::seed_from_u64(7));
+        assert!(first == shuffled(&albums, &mut StdRng::seed_from_u64(7)));
+        assert!(first != albums);
+        // Every album is still there exactly once
+        let mut sorted = first.clone();
+        sorted.sort_by_key(|album| album.0);
+        assert!(sorted == albums);
+    }
+}
@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use sdl2::rect::Rect;
+use sdl2::video::Window;
+use sdl2::VideoSubsystem;
+use serde::{Deserialize, Serialize};
+
+use crate::View;
+
+/// The window's position and view from the last time micromusic was closed, so that they can be restored on launch.
+/// Saved as `state.json` in the data directory (e.g. ~/Library/Application Support/com.breitnw.micromusic/).
+#[derive(Serialize, Deserialize)]
+pub struct WindowState {
+    x: i32,
+    y: i32,
+    /// The index of the display that the window was on
+    display: i32,
+    pub view: View,
+}
+
+impl WindowState {
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("com", "breitnw", "micromusic")
+            .map(|project_dirs| project_dirs.data_dir().join("state.json"))
+    }
+
+    /// Loads the saved state, or returns None if there isn't any or it can't be read
+    pub fn load() -> Option<Self> {
+        let file = std::fs::read_to_string(Self::path()?).ok()?;
+        serde_json::from_str(&file)
+            .map_err(|e| println!("Ignoring invalid window state: {e}"))
+            .ok()
+    }
+
+    /// Gets the current state of the window
+    pub fn from_window(window: &Window, view: View) -> Result<Self, String> {
+        let (x, y) = window.position();
+        Ok(WindowState {
+            x,
+            y,
+            display: window.display_index()?,
+            view,
+        })
+    }
+
+    /// Saves the state so it can be restored the next time micromusic is launched
+    pub fn save(&self) -> Result<(), String> {
+        let path = Self::path().ok_or("Unable to get path to data directory")?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&path, json).map_err(|e| format!("Unable to write {}: {e}", path.display()))
+    }
+
+    /// Gets the saved window position if the window would still be visible there. Returns None if the display it was
+    /// on has been disconnected, or if the window's center is no longer within that display's bounds (e.g. because
+    /// the resolution changed).
+    pub fn position(&self, video_subsystem: &VideoSubsystem, width: u32, height: u32) -> Option<(i32, i32)> {
+        if self.display >= video_subsystem.num_video_displays().ok()? {
+            return None;
+        }
+        let bounds = video_subsystem.display_bounds(self.display).ok()?;
+        let window_rect = Rect::new(self.x, self.y, width, height);
+        bounds
+            .contains_point(window_rect.center())
+            .then_some((self.x, self.y))
+    }
+}
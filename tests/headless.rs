@@ -0,0 +1,80 @@
+//! Golden image tests, which render frames headless against the mock backend and compare them with the reference
+//! frames in `tests/golden`. A missing reference frame fails the test. Setting `MICROMUSIC_UPDATE_GOLDEN` records every
+//! reference frame from the render instead, so that new views and intended changes to the layout can be committed.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Renders frames headless with the given arguments, returning the directory they were written to. The config, data
+/// and cache directories point into a fresh directory, so the user's own config and artwork cache aren't used.
+fn render(name: &str, args: &[&str]) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("micromusic-golden-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let output_dir = root.join("frames");
+
+    let status = Command::new(env!("CARGO_BIN_EXE_micromusic"))
+        .args(["--backend", "mock", "--headless"])
+        .arg(&output_dir)
+        .args(args)
+        .env("HOME", &root)
+        .env("XDG_CONFIG_HOME", root.join("config"))
+        .env("XDG_DATA_HOME", root.join("data"))
+        .env("XDG_CACHE_HOME", root.join("cache"))
+        .status()
+        .expect("Unable to run micromusic");
+    assert!(status.success(), "micromusic exited with {status}");
+    output_dir
+}
+
+/// Compares a rendered frame with its reference frame, or records the reference frame when `MICROMUSIC_UPDATE_GOLDEN`
+/// is set
+fn assert_matches_golden(output_dir: &Path, name: &str, frame: u32) {
+    let file_name = format!("frame_{frame:04}.png");
+    let rendered_path = output_dir.join(&file_name);
+    let golden_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name).join(&file_name);
+
+    if std::env::var_os("MICROMUSIC_UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_path.parent().unwrap()).unwrap();
+        std::fs::copy(&rendered_path, &golden_path).unwrap();
+        eprintln!("Recorded {}", golden_path.display());
+        return;
+    }
+    assert!(
+        golden_path.exists(),
+        "{name}/{file_name} has no reference frame, run the tests with MICROMUSIC_UPDATE_GOLDEN=1 to record it"
+    );
+
+    let rendered = image::open(&rendered_path).unwrap().to_rgb8();
+    let golden = image::open(&golden_path).unwrap().to_rgb8();
+    assert_eq!(rendered.dimensions(), golden.dimensions(), "{name}/{file_name} changed size");
+    let different_pixels = rendered.pixels().zip(golden.pixels()).filter(|(a, b)| a != b).count();
+    assert!(
+        different_pixels == 0,
+        "{name}/{file_name} differs from the reference frame in {different_pixels} pixels (rendered frame: {})",
+        rendered_path.display()
+    );
+}
+
+#[test]
+fn miniplayer() {
+    let output_dir = render("miniplayer", &[]);
+    assert_matches_golden(&output_dir, "miniplayer", 0);
+}
+
+#[test]
+fn miniplayer_overlay() {
+    // The info text scrolls and the progress bar moves while the track plays
+    let output_dir = render("miniplayer_overlay", &["--mouse", "100,100", "--frames", "30"]);
+    for frame in [0, 29] {
+        assert_matches_golden(&output_dir, "miniplayer_overlay", frame);
+    }
+}
+
+#[test]
+fn album_view() {
+    // The thumbnails slide into place over the first frames
+    let output_dir = render("album_view", &["--view", "album", "--mouse", "100,100", "--frames", "30"]);
+    for frame in [0, 29] {
+        assert_matches_golden(&output_dir, "album_view", frame);
+    }
+}